@@ -0,0 +1,472 @@
+// Subcommand implementations
+// Human-readable progress goes to stdout with the same styling as the original demo;
+// with `--json` only a single JSON document is printed.
+
+use super::json::{array, JsonObject};
+use super::{Cli, Command, USAGE};
+use console::style;
+use numero::fingerprint::config::PRESETS;
+#[cfg(feature = "plot")]
+use numero::fingerprint::fingerprint::analyze;
+use numero::index::store::{inspect_store, load_fingerprints, StoreKind, MAGIC};
+use numero::utils::calculate_audio_stats;
+use numero::{
+    finger_print, read_audio_file, BandLayout, BatchIndexer, FileOutcome, FingerprintConfig,
+    FingerprintIndex, Pairing, PeakPicker,
+};
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Mutex;
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+pub fn run(cli: &Cli) -> CliResult<()> {
+    match &cli.command {
+        Command::Index {
+            dir,
+            db,
+            preset,
+            jobs,
+        } => index(dir, db, preset.as_deref(), *jobs, cli.json),
+        Command::Query { clip, db, top } => query(clip, db, *top, cli.json),
+        Command::Inspect { file } => inspect(file, cli.json),
+        Command::Plot { file, out, preset } => plot(file, out, preset.as_deref(), cli.json),
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    }
+}
+
+fn index(dir: &Path, db: &Path, preset: Option<&str>, jobs: usize, json: bool) -> CliResult<()> {
+    // Update an existing database, otherwise start a new one
+    let mut index = if db.exists() {
+        let header = inspect_store(db).map_err(|e| format!("{}: {}", db.display(), e))?;
+        if let Some(name) = preset {
+            if resolve_preset(Some(name))? != header.config {
+                return Err(format!(
+                    "{} was built with a different config than preset `{}`",
+                    db.display(),
+                    name
+                )
+                .into());
+            }
+        }
+        FingerprintIndex::load(db, &header.config)
+            .map_err(|e| format!("{}: {}", db.display(), e))?
+    } else {
+        FingerprintIndex::new(resolve_preset(preset)?)
+    };
+    // Paths whose failure was already printed as it happened
+    let reported = Mutex::new(BTreeSet::new());
+    let report = BatchIndexer::new()
+        .with_threads(jobs)
+        .index_dir(dir, &mut index, |progress| {
+            if json {
+                return;
+            }
+            let counter = style(format!("[{}/{}]", progress.completed, progress.total)).dim();
+            let path = progress.path.display();
+            match progress.outcome {
+                FileOutcome::Indexed(count) => println!(
+                    "{} {} Indexed {} ({} fingerprints)",
+                    counter,
+                    style("✓").green().bold(),
+                    path,
+                    count
+                ),
+                FileOutcome::Unchanged => {
+                    println!("{} {} Unchanged {}", counter, style("=").dim(), path)
+                }
+                FileOutcome::Failed(e) => {
+                    reported.lock().unwrap().insert(progress.path.to_path_buf());
+                    println!(
+                        "{} {} Skipped {}: {}",
+                        counter,
+                        style("✗").red().bold(),
+                        path,
+                        e
+                    )
+                }
+            }
+        })
+        .map_err(|e| format!("{}: {}", dir.display(), e))?;
+
+    let added: Vec<String> = report
+        .added
+        .iter()
+        .map(|file| {
+            JsonObject::new()
+                .int("track_id", file.track_id as i64)
+                .string("name", &file.path.display().to_string())
+                .int("fingerprints", file.fingerprints as i64)
+                .float("duration_secs", file.duration_secs)
+                .raw(
+                    "replaced",
+                    file.replaced
+                        .map_or("null".to_string(), |id| id.to_string()),
+                )
+                .build()
+        })
+        .collect();
+    let removed: Vec<String> = report
+        .removed
+        .iter()
+        .map(|file| {
+            JsonObject::new()
+                .int("track_id", file.track_id as i64)
+                .string("name", &file.path.display().to_string())
+                .build()
+        })
+        .collect();
+    let errors: Vec<String> = report
+        .skipped
+        .iter()
+        .map(|file| {
+            JsonObject::new()
+                .string("file", &file.path.display().to_string())
+                .string("error", &file.error.to_string())
+                .build()
+        })
+        .collect();
+    if !json {
+        let reported = reported.into_inner().unwrap();
+        for skipped in report
+            .skipped
+            .iter()
+            .filter(|file| !reported.contains(&file.path))
+        {
+            println!(
+                "{} Skipped {}: {}",
+                style("✗").red().bold(),
+                skipped.path.display(),
+                skipped.error
+            );
+        }
+        for file in &report.removed {
+            println!(
+                "{} Removed {} (source file is gone)",
+                style("-").yellow().bold(),
+                file.path.display()
+            );
+        }
+    }
+
+    index
+        .save(db)
+        .map_err(|e| format!("{}: {}", db.display(), e))?;
+
+    if json {
+        println!(
+            "{}",
+            JsonObject::new()
+                .string("db", &db.display().to_string())
+                .int("tracks", index.track_count() as i64)
+                .int("hashes", index.hash_count() as i64)
+                .raw("added", array(added))
+                .raw("removed", array(removed))
+                .int("unchanged", report.unchanged.len() as i64)
+                .raw("errors", array(errors))
+                .build()
+        );
+    } else {
+        println!(
+            "\n{} {} added, {} removed, {} unchanged",
+            style("Update:").blue().bold(),
+            report.added.len(),
+            report.removed.len(),
+            report.unchanged.len()
+        );
+        println!(
+            "{} {} tracks, {} distinct hashes in {}",
+            style("Index:").blue().bold(),
+            index.track_count(),
+            index.hash_count(),
+            db.display()
+        );
+    }
+
+    Ok(())
+}
+
+fn query(clip: &Path, db: &Path, top: usize, json: bool) -> CliResult<()> {
+    let header = inspect_store(db).map_err(|e| format!("{}: {}", db.display(), e))?;
+    let index = FingerprintIndex::load(db, &header.config)
+        .map_err(|e| format!("{}: {}", db.display(), e))?;
+
+    let (samples, sample_rate) = read_audio(clip)?;
+    let clip_duration = samples.len() as f64 / sample_rate as f64;
+    let set = finger_print(&samples, sample_rate, index.config())?;
+    let matches = index.query(&set)?;
+
+    if json {
+        let items = matches.iter().take(top).map(|m| {
+            JsonObject::new()
+                .int("track_id", m.track_id as i64)
+                .string("name", &m.name)
+                .float("offset_secs", m.offset_secs)
+                .float("covered_secs", m.covered_secs)
+                .int("votes", m.votes as i64)
+                .float("score", m.score)
+                .float("significance", m.significance)
+                .int("matched_hashes", m.matched_hashes as i64)
+                .build()
+        });
+        println!(
+            "{}",
+            JsonObject::new()
+                .string("clip", &clip.display().to_string())
+                .float("duration_secs", clip_duration)
+                .int("fingerprints", set.len() as i64)
+                .raw("matches", array(items))
+                .build()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{} Generated fingerprint for clip ({:.2} seconds, {} fingerprints)",
+        style("✓").green().bold(),
+        clip_duration,
+        set.len()
+    );
+
+    if matches.is_empty() {
+        println!("{}", style("No match found.").bold().red());
+        return Ok(());
+    }
+
+    for (rank, m) in matches.iter().take(top).enumerate() {
+        println!(
+            "{:>2}. {} at {:.2} seconds ({} votes, {:.1}% of hashes, {:.2}s covered, significance {:.1})",
+            rank + 1,
+            style(&m.name).cyan().bold(),
+            m.offset_secs,
+            m.votes,
+            m.score * 100.0,
+            m.covered_secs,
+            m.significance
+        );
+    }
+
+    Ok(())
+}
+
+fn inspect(file: &Path, json: bool) -> CliResult<()> {
+    if is_store_file(file) {
+        inspect_store_file(file, json)
+    } else {
+        inspect_audio_file(file, json)
+    }
+}
+
+fn inspect_store_file(file: &Path, json: bool) -> CliResult<()> {
+    let header = inspect_store(file).map_err(|e| format!("{}: {}", file.display(), e))?;
+
+    let (kind, counts) = match header.kind {
+        StoreKind::Index => {
+            let index = FingerprintIndex::load(file, &header.config)
+                .map_err(|e| format!("{}: {}", file.display(), e))?;
+            (
+                "index",
+                vec![
+                    ("tracks", index.track_count()),
+                    ("hashes", index.hash_count()),
+                ],
+            )
+        }
+        StoreKind::Fingerprints => {
+            let set = load_fingerprints(file, &header.config)
+                .map_err(|e| format!("{}: {}", file.display(), e))?;
+            ("fingerprints", vec![("fingerprints", set.len())])
+        }
+    };
+
+    if json {
+        let mut object = JsonObject::new()
+            .string("file", &file.display().to_string())
+            .string("kind", kind)
+            .int("version", header.version as i64)
+            .raw("config", config_json(&header.config));
+        for (key, count) in counts {
+            object = object.int(key, count as i64);
+        }
+        println!("{}", object.build());
+    } else {
+        println!(
+            "{} numero {} store (format v{})",
+            style("✓").green().bold(),
+            kind,
+            header.version
+        );
+        println!("config: {:?}", header.config);
+        for (key, count) in counts {
+            println!("{}: {}", key, count);
+        }
+    }
+
+    Ok(())
+}
+
+fn inspect_audio_file(file: &Path, json: bool) -> CliResult<()> {
+    let (samples, sample_rate) = read_audio(file)?;
+    let duration = samples.len() as f64 / sample_rate as f64;
+    let stats = calculate_audio_stats(&samples);
+    let set = finger_print(&samples, sample_rate, &FingerprintConfig::default())?;
+
+    if json {
+        println!(
+            "{}",
+            JsonObject::new()
+                .string("file", &file.display().to_string())
+                .string("kind", "audio")
+                .int("sample_rate", sample_rate as i64)
+                .float("duration_secs", duration)
+                .int("min", stats.min as i64)
+                .int("max", stats.max as i64)
+                .float("avg_amplitude", stats.avg_amplitude as f64)
+                .float("zero_crossing_rate", stats.zero_crossing_rate as f64)
+                .int("fingerprints", set.len() as i64)
+                .build()
+        );
+    } else {
+        println!(
+            "{} {} ({} Hz, {:.2} seconds)",
+            style("✓").green().bold(),
+            file.display(),
+            sample_rate,
+            duration
+        );
+        println!("range: {} .. {}", stats.min, stats.max);
+        println!("average amplitude: {:.1}", stats.avg_amplitude);
+        println!("zero crossing rate: {:.4}", stats.zero_crossing_rate);
+        println!("fingerprints: {}", set.len());
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "plot"))]
+fn plot(_file: &Path, _out: &Path, _preset: Option<&str>, _json: bool) -> CliResult<()> {
+    Err("numero was built without the `plot` feature".into())
+}
+
+#[cfg(feature = "plot")]
+fn plot(file: &Path, out: &Path, preset: Option<&str>, json: bool) -> CliResult<()> {
+    let config = resolve_preset(preset)?;
+    let (samples, sample_rate) = read_audio(file)?;
+    let analysis = analyze(&samples, sample_rate, &config)?;
+
+    analysis.plot(&config, out)?;
+
+    if json {
+        println!(
+            "{}",
+            JsonObject::new()
+                .string("file", &file.display().to_string())
+                .string("out", &out.display().to_string())
+                .int("frames", analysis.spectrogram.len() as i64)
+                .int("peaks", analysis.peaks.len() as i64)
+                .build()
+        );
+    } else {
+        println!(
+            "{} Wrote spectrogram with {} peaks to {}",
+            style("✓").green().bold(),
+            analysis.peaks.len(),
+            out.display()
+        );
+    }
+
+    Ok(())
+}
+
+fn resolve_preset(preset: Option<&str>) -> CliResult<FingerprintConfig> {
+    match preset {
+        None => Ok(FingerprintConfig::default()),
+        Some(name) => FingerprintConfig::preset(name).ok_or_else(|| {
+            format!(
+                "Unknown preset `{}` (expected one of: {})",
+                name,
+                PRESETS.join(", ")
+            )
+            .into()
+        }),
+    }
+}
+
+fn read_audio(path: &Path) -> CliResult<(Vec<i16>, u32)> {
+    Ok(read_audio_file(path).map_err(|e| format!("{}: {}", path.display(), e))?)
+}
+
+fn is_store_file(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map(|_| magic == MAGIC)
+        .unwrap_or(false)
+}
+
+fn config_json(config: &FingerprintConfig) -> String {
+    JsonObject::new()
+        .int("target_sample_rate", config.target_sample_rate as i64)
+        .int("filter_taps", config.filter_taps as i64)
+        .int("frame_size", config.frame_size as i64)
+        .int("hop_size", config.hop_size as i64)
+        .int("num_bands", config.num_bands as i64)
+        .int("target_zone_frames", config.target_zone_frames as i64)
+        .float("threshold_multiplier", config.threshold_multiplier)
+        .string("normalization", config.normalization.name())
+        .raw("peak_picker", peak_picker_json(&config.peak_picker))
+        .raw("band_layout", band_layout_json(&config.band_layout))
+        .raw(
+            "hash_codec",
+            JsonObject::new()
+                .int("freq_bits", config.hash_codec.freq_bits as i64)
+                .int("dt_bits", config.hash_codec.dt_bits as i64)
+                .int("freq_step", config.hash_codec.freq_step as i64)
+                .build(),
+        )
+        .raw("pairing", pairing_json(&config.pairing))
+        .string("hash_family", config.hash_family.name())
+        .build()
+}
+
+fn pairing_json(pairing: &Pairing) -> String {
+    let limit = |value: Option<usize>| value.map_or("null".to_string(), |v| v.to_string());
+    JsonObject::new()
+        .raw("max_fan_out", limit(pairing.max_fan_out))
+        .int("min_dt", pairing.min_dt as i64)
+        .raw("max_freq_distance", limit(pairing.max_freq_distance))
+        .raw("strongest_first", pairing.strongest_first.to_string())
+        .build()
+}
+
+fn band_layout_json(layout: &BandLayout) -> String {
+    let object = JsonObject::new().string("kind", layout.name());
+    match layout {
+        BandLayout::Custom(edges) => object
+            .raw("edges_hz", array(edges.iter().map(|edge| edge.to_string())))
+            .build(),
+        _ => object.build(),
+    }
+}
+
+fn peak_picker_json(picker: &PeakPicker) -> String {
+    match *picker {
+        PeakPicker::BandMax => JsonObject::new().string("kind", "band-max").build(),
+        PeakPicker::Constellation {
+            neighborhood_frames,
+            neighborhood_bins,
+            peaks_per_second,
+        } => JsonObject::new()
+            .string("kind", "constellation")
+            .int("neighborhood_frames", neighborhood_frames as i64)
+            .int("neighborhood_bins", neighborhood_bins as i64)
+            .float("peaks_per_second", peaks_per_second)
+            .build(),
+    }
+}
@@ -0,0 +1,77 @@
+// Minimal JSON writer for the `--json` output mode
+
+use std::fmt::Write;
+
+/// Builds a JSON object field by field, preserving insertion order
+#[derive(Debug, Default)]
+pub struct JsonObject {
+    fields: Vec<(String, String)>,
+}
+
+impl JsonObject {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn string(mut self, key: &str, value: &str) -> Self {
+        self.fields.push((key.to_string(), escape(value)));
+        self
+    }
+
+    pub fn int(mut self, key: &str, value: i64) -> Self {
+        self.fields.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn float(mut self, key: &str, value: f64) -> Self {
+        let json = if value.is_finite() {
+            value.to_string()
+        } else {
+            "null".to_string()
+        };
+        self.fields.push((key.to_string(), json));
+        self
+    }
+
+    /// Adds an already serialized JSON value (object or array)
+    pub fn raw(mut self, key: &str, json: String) -> Self {
+        self.fields.push((key.to_string(), json));
+        self
+    }
+
+    pub fn build(self) -> String {
+        let fields: Vec<String> = self
+            .fields
+            .into_iter()
+            .map(|(key, value)| format!("{}:{}", escape(&key), value))
+            .collect();
+        format!("{{{}}}", fields.join(","))
+    }
+}
+
+/// Serializes already serialized JSON values as an array
+pub fn array<I: IntoIterator<Item = String>>(items: I) -> String {
+    let items: Vec<String> = items.into_iter().collect();
+    format!("[{}]", items.join(","))
+}
+
+/// Quotes and escapes a string
+pub fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,280 @@
+// Command-line interface
+// Parses `numero <subcommand> [args] [--flags]` into a `Cli` value that main dispatches on.
+
+pub mod commands;
+pub mod json;
+
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: numero <command> [options]
+
+Commands:
+  index <dir> --db <file> [--preset <name>] [--jobs <n>]
+                                              Bring an index up to date with the audio files under <dir>:
+                                              new and changed files are fingerprinted, <n> at a time
+                                              (default: one per CPU), and deleted ones are removed
+  query <clip> --db <file> [--top <n>]        Identify a clip against an index
+  inspect <file>                              Describe an audio file or a numero store file
+  plot <file> [--out <png>] [--preset <name>] Plot the spectrogram and peaks of an audio file
+
+Options:
+  --json       Print machine-readable JSON instead of text
+  -h, --help   Show this message
+
+Presets: music (default), speech, broadcast";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Index {
+        dir: PathBuf,
+        db: PathBuf,
+        preset: Option<String>,
+        /// Worker threads; 0 uses one per CPU
+        jobs: usize,
+    },
+    Query {
+        clip: PathBuf,
+        db: PathBuf,
+        top: usize,
+    },
+    Inspect {
+        file: PathBuf,
+    },
+    Plot {
+        file: PathBuf,
+        out: PathBuf,
+        preset: Option<String>,
+    },
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cli {
+    pub command: Command,
+    pub json: bool,
+}
+
+/// Parses the arguments following the program name
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Cli, String> {
+    let mut positional = Vec::new();
+    let mut db = None;
+    let mut preset = None;
+    let mut out = None;
+    let mut top = None;
+    let mut jobs = None;
+    let mut json = false;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                return Ok(Cli {
+                    command: Command::Help,
+                    json,
+                })
+            }
+            "--json" => json = true,
+            "--db" => db = Some(PathBuf::from(flag_value(&mut args, "--db")?)),
+            "--preset" => preset = Some(flag_value(&mut args, "--preset")?),
+            "--out" => out = Some(PathBuf::from(flag_value(&mut args, "--out")?)),
+            "--top" => {
+                let value = flag_value(&mut args, "--top")?;
+                top = Some(
+                    value
+                        .parse::<usize>()
+                        .map_err(|_| format!("Invalid value for --top: {}", value))?,
+                );
+            }
+            "--jobs" => {
+                let value = flag_value(&mut args, "--jobs")?;
+                jobs = Some(
+                    value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| format!("Invalid value for --jobs: {}", value))?,
+                );
+            }
+            flag if flag.starts_with("--") => return Err(format!("Unknown option: {}", flag)),
+            _ => positional.push(arg),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let Some(name) = positional.next() else {
+        return Ok(Cli {
+            command: Command::Help,
+            json,
+        });
+    };
+    let target = positional.next().map(PathBuf::from);
+    if let Some(extra) = positional.next() {
+        return Err(format!("Unexpected argument: {}", extra));
+    }
+
+    let command = match name.as_str() {
+        "index" => Command::Index {
+            dir: required(target, "index", "<dir>")?,
+            db: required(db, "index", "--db <file>")?,
+            preset,
+            jobs: jobs.unwrap_or(0),
+        },
+        "query" => Command::Query {
+            clip: required(target, "query", "<clip>")?,
+            db: required(db, "query", "--db <file>")?,
+            top: top.unwrap_or(5),
+        },
+        "inspect" => Command::Inspect {
+            file: required(target, "inspect", "<file>")?,
+        },
+        "plot" => Command::Plot {
+            file: required(target, "plot", "<file>")?,
+            out: out.unwrap_or_else(|| PathBuf::from("spectrogram.png")),
+            preset,
+        },
+        "help" => Command::Help,
+        other => return Err(format!("Unknown command: {}", other)),
+    };
+
+    Ok(Cli { command, json })
+}
+
+fn flag_value<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("Missing value for {}", flag))
+}
+
+fn required<T>(value: Option<T>, command: &str, what: &str) -> Result<T, String> {
+    value.ok_or_else(|| format!("`{}` requires {}", command, what))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn command(args: &[&str]) -> Command {
+        parse(args).unwrap().command
+    }
+
+    #[test]
+    fn subcommands_and_their_defaults() {
+        assert_eq!(
+            command(&["index", "songs", "--db", "db.numr"]),
+            Command::Index {
+                dir: PathBuf::from("songs"),
+                db: PathBuf::from("db.numr"),
+                preset: None,
+                jobs: 0,
+            }
+        );
+        assert_eq!(
+            command(&["query", "clip.wav", "--db", "db.numr"]),
+            Command::Query {
+                clip: PathBuf::from("clip.wav"),
+                db: PathBuf::from("db.numr"),
+                top: 5,
+            }
+        );
+        assert_eq!(
+            command(&["inspect", "db.numr"]),
+            Command::Inspect {
+                file: PathBuf::from("db.numr"),
+            }
+        );
+        assert_eq!(
+            command(&["plot", "song.wav"]),
+            Command::Plot {
+                file: PathBuf::from("song.wav"),
+                out: PathBuf::from("spectrogram.png"),
+                preset: None,
+            }
+        );
+    }
+
+    #[test]
+    fn flags_go_anywhere_after_the_program_name() {
+        let cli = parse(&[
+            "--json", "index", "--jobs", "3", "songs", "--preset", "speech", "--db", "db.numr",
+        ])
+        .unwrap();
+        assert!(cli.json);
+        assert_eq!(
+            cli.command,
+            Command::Index {
+                dir: PathBuf::from("songs"),
+                db: PathBuf::from("db.numr"),
+                preset: Some("speech".to_string()),
+                jobs: 3,
+            }
+        );
+
+        assert_eq!(
+            command(&["query", "--top", "2", "--db", "db.numr", "clip.wav"]),
+            Command::Query {
+                clip: PathBuf::from("clip.wav"),
+                db: PathBuf::from("db.numr"),
+                top: 2,
+            }
+        );
+        assert_eq!(
+            command(&["plot", "song.wav", "--out", "song.png"]),
+            Command::Plot {
+                file: PathBuf::from("song.wav"),
+                out: PathBuf::from("song.png"),
+                preset: None,
+            }
+        );
+    }
+
+    #[test]
+    fn help_is_shown_without_a_command_or_when_asked() {
+        for args in [
+            &[][..],
+            &["help"],
+            &["-h"],
+            &["--json"],
+            &["index", "--help"],
+        ] {
+            assert_eq!(command(args), Command::Help, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn jobs_must_be_a_positive_number() {
+        for value in ["0", "-1", "two", ""] {
+            assert_eq!(
+                parse(&["index", "songs", "--db", "db.numr", "--jobs", value]),
+                Err(format!("Invalid value for --jobs: {}", value))
+            );
+        }
+        assert_eq!(
+            parse(&["query", "clip.wav", "--db", "db.numr", "--top", "x"]),
+            Err("Invalid value for --top: x".to_string())
+        );
+    }
+
+    #[test]
+    fn malformed_command_lines_are_explained() {
+        let cases: [(&[&str], &str); 7] = [
+            (&["index", "songs"], "`index` requires --db <file>"),
+            (&["index", "--db", "db.numr"], "`index` requires <dir>"),
+            (&["query", "clip.wav"], "`query` requires --db <file>"),
+            (&["inspect"], "`inspect` requires <file>"),
+            (&["index", "songs", "--db"], "Missing value for --db"),
+            (&["inspect", "a", "b"], "Unexpected argument: b"),
+            (&["inspect", "a", "--verbose"], "Unknown option: --verbose"),
+        ];
+        for (args, message) in cases {
+            assert_eq!(parse(args), Err(message.to_string()), "{:?}", args);
+        }
+        assert_eq!(
+            parse(&["identify", "clip.wav"]),
+            Err("Unknown command: identify".to_string())
+        );
+    }
+}
@@ -1,26 +1,213 @@
 // FFT and Spectrogram Creation
 // Each windowed frame is transformed into the frequency domain using the Fast Fourier Transform (FFT):
 // FFT Computation: In fft.rs, ComputeFFT computes the FFT of a real-valued frame using the Gonum DSP library. Since the FFT of a real signal is symmetric, only the first half of the magnitude spectrum is kept.
+//
+// `SpectrumAnalyzer` is the reusable form: it plans once per frame size and computes the
+// real-input FFT of an N-sample frame with a single N/2-point complex FFT. Even samples go in
+// the real part and odd samples in the imaginary part; the two interleaved half-length spectra
+// are then separated and recombined with one twiddle factor per bin.
 
-use rustfft::{num_complex::Complex, FftPlanner};
+use rayon::prelude::*;
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+use std::cell::RefCell;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// Added to the power before taking the logarithm so silent bins stay finite
+const LOG_POWER_FLOOR: f64 = 1e-20;
+
+thread_local! {
+    // rustfft's planner caches every plan it has made, so keeping one per thread
+    // makes repeated frame sizes free to plan
+    static PLANNER: RefCell<FftPlanner<f64>> = RefCell::new(FftPlanner::new());
+}
+
+fn plan_forward(len: usize) -> Arc<dyn Fft<f64>> {
+    PLANNER.with(|planner| planner.borrow_mut().plan_fft_forward(len))
+}
 
 // computes the FFT of a real valued frame. returns the magnitude spectrum
 // (only the first half is returned since the input is real-valued).
 pub fn compute_fft(frame: Vec<f32>) -> Vec<f64> {
-    let n = frame.len();
-    let mut planner = FftPlanner::new();
-    let fft = planner.plan_fft_forward(n);
+    let frame: Vec<f64> = frame.iter().map(|&x| x as f64).collect();
+    SpectrumAnalyzer::new(frame.len(), SpectrumScale::Magnitude).process(&frame)
+}
 
-    let mut complex_frame: Vec<Complex<f64>> =
-        frame.iter().map(|&x| Complex::new(x as f64, 0.0)).collect();
-    fft.process(&mut complex_frame);
+/// What each spectrum bin holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpectrumScale {
+    /// |X[k]|
+    Magnitude,
+    /// |X[k]|²
+    Power,
+    /// 10·log10(|X[k]|²), in dB
+    LogPower,
+}
 
-    let half = n / 2 + 1;
-    let mut magnitudes = Vec::with_capacity(half);
+/// Per-worker buffers, so processing a frame does not allocate
+#[derive(Debug, Clone)]
+pub struct SpectrumScratch {
+    buffer: Vec<Complex<f64>>,
+    fft_scratch: Vec<Complex<f64>>,
+}
+
+/// Computes one-sided spectra of fixed-size real frames, with an optional window
+#[derive(Clone)]
+pub struct SpectrumAnalyzer {
+    frame_size: usize,
+    scale: SpectrumScale,
+    window: Option<Vec<f64>>,
+    fft: Arc<dyn Fft<f64>>,
+    // exp(-2πik/N) for k in 0..=N/2, only used by the packed (even N) path
+    twiddles: Vec<Complex<f64>>,
+}
 
-    for i in 0..half {
-        magnitudes.push(complex_frame[i].norm());
+impl SpectrumAnalyzer {
+    pub fn new(frame_size: usize, scale: SpectrumScale) -> Self {
+        assert!(frame_size > 0, "frame size must be positive");
+
+        // Even sizes use the packed half-length transform, odd sizes a full complex one
+        let packed = frame_size.is_multiple_of(2);
+        let fft = plan_forward(if packed { frame_size / 2 } else { frame_size });
+        let twiddles = if packed {
+            (0..=frame_size / 2)
+                .map(|k| Complex::from_polar(1.0, -2.0 * PI * k as f64 / frame_size as f64))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        SpectrumAnalyzer {
+            frame_size,
+            scale,
+            window: None,
+            fft,
+            twiddles,
+        }
     }
 
-    magnitudes
+    /// Multiplies every frame by `window` before transforming it
+    pub fn with_window(mut self, window: Vec<f64>) -> Self {
+        assert_eq!(
+            window.len(),
+            self.frame_size,
+            "window length must equal the frame size"
+        );
+        self.window = Some(window);
+        self
+    }
+
+    pub fn frame_size(&self) -> usize {
+        self.frame_size
+    }
+
+    /// Number of bins in each spectrum, `frame_size / 2 + 1`
+    pub fn num_bins(&self) -> usize {
+        self.frame_size / 2 + 1
+    }
+
+    pub fn scale(&self) -> SpectrumScale {
+        self.scale
+    }
+
+    pub fn scratch(&self) -> SpectrumScratch {
+        SpectrumScratch {
+            buffer: vec![Complex::new(0.0, 0.0); self.fft.len()],
+            fft_scratch: vec![Complex::new(0.0, 0.0); self.fft.get_inplace_scratch_len()],
+        }
+    }
+
+    /// Spectrum of a single frame
+    pub fn process(&self, frame: &[f64]) -> Vec<f64> {
+        let mut output = vec![0.0; self.num_bins()];
+        self.process_into(frame, &mut self.scratch(), &mut output);
+        output
+    }
+
+    /// Spectrum of a single frame, written into `output` (`num_bins` long)
+    pub fn process_into(&self, frame: &[f64], scratch: &mut SpectrumScratch, output: &mut [f64]) {
+        assert_eq!(frame.len(), self.frame_size, "frame has the wrong length");
+        assert_eq!(output.len(), self.num_bins(), "output has the wrong length");
+
+        let sample = |i: usize| match &self.window {
+            Some(window) => frame[i] * window[i],
+            None => frame[i],
+        };
+
+        if self.twiddles.is_empty() {
+            for (i, slot) in scratch.buffer.iter_mut().enumerate() {
+                *slot = Complex::new(sample(i), 0.0);
+            }
+            self.fft
+                .process_with_scratch(&mut scratch.buffer, &mut scratch.fft_scratch);
+            for (out, bin) in output.iter_mut().zip(&scratch.buffer) {
+                *out = self.scaled(bin.norm_sqr());
+            }
+            return;
+        }
+
+        // Pack even samples into the real part and odd samples into the imaginary part
+        for (i, slot) in scratch.buffer.iter_mut().enumerate() {
+            *slot = Complex::new(sample(2 * i), sample(2 * i + 1));
+        }
+        self.fft
+            .process_with_scratch(&mut scratch.buffer, &mut scratch.fft_scratch);
+
+        // Z[k] = E[k] + iO[k], where E and O are the spectra of the even and odd samples,
+        // and X[k] = E[k] + exp(-2πik/N)·O[k]
+        let half = self.fft.len();
+        let z = &scratch.buffer;
+        for (k, out) in output.iter_mut().enumerate() {
+            let zk = z[k % half];
+            let zc = z[(half - k) % half].conj();
+            let even = (zk + zc) * 0.5;
+            let odd = (zk - zc) * Complex::new(0.0, -0.5);
+            *out = self.scaled((even + self.twiddles[k] * odd).norm_sqr());
+        }
+    }
+
+    /// Spectra of every full frame of `signal`, starting every `hop_size` samples.
+    /// Frames are processed in parallel, each rayon worker reusing its own scratch buffers.
+    pub fn spectrogram(&self, signal: &[f64], hop_size: usize) -> Vec<Vec<f64>> {
+        assert!(hop_size > 0, "hop size must be positive");
+        if signal.len() < self.frame_size {
+            return Vec::new();
+        }
+
+        let num_frames = (signal.len() - self.frame_size) / hop_size + 1;
+        (0..num_frames)
+            .into_par_iter()
+            .map_init(
+                || self.scratch(),
+                |scratch, index| {
+                    let start = index * hop_size;
+                    let mut spectrum = vec![0.0; self.num_bins()];
+                    self.process_into(
+                        &signal[start..start + self.frame_size],
+                        scratch,
+                        &mut spectrum,
+                    );
+                    spectrum
+                },
+            )
+            .collect()
+    }
+
+    fn scaled(&self, power: f64) -> f64 {
+        match self.scale {
+            SpectrumScale::Magnitude => power.sqrt(),
+            SpectrumScale::Power => power,
+            SpectrumScale::LogPower => 10.0 * (power + LOG_POWER_FLOOR).log10(),
+        }
+    }
+}
+
+impl std::fmt::Debug for SpectrumAnalyzer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SpectrumAnalyzer")
+            .field("frame_size", &self.frame_size)
+            .field("scale", &self.scale)
+            .field("windowed", &self.window.is_some())
+            .finish()
+    }
 }
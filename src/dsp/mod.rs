@@ -1,3 +1,4 @@
 pub mod fft;
-pub mod filter;
+pub mod resample;
+#[cfg(feature = "plot")]
 pub mod viz;
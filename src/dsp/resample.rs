@@ -0,0 +1,262 @@
+// Rational resampling
+// Converts a signal from any input rate to any output rate whose ratio reduces to L/M (up/down).
+// Output sample n sits at input time n * M / L; its fractional position can only take L distinct
+// values, so the windowed-sinc interpolation filter is precomputed once per phase (polyphase).
+// The filter's cutoff is placed below the lower of the two Nyquist frequencies, so the same
+// filter both interpolates and removes anything that would alias after downsampling.
+// Only retained output samples are computed, so downsampling costs in proportion to the output
+// rate rather than the input rate.
+
+use std::f64::consts::PI;
+
+/// Fraction of the lower Nyquist frequency kept by the anti-aliasing filter
+const PASSBAND: f64 = 0.95;
+
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    input_rate: u32,
+    output_rate: u32,
+    up: usize,
+    down: usize,
+    half_width: usize,
+    // One filter per phase, each `2 * half_width + 1` taps long
+    phases: Vec<Vec<f64>>,
+}
+
+impl Resampler {
+    /// Creates a resampler whose filter spans `taps` input samples (rounded up to an odd number)
+    pub fn new(input_rate: u32, output_rate: u32, taps: usize) -> Self {
+        assert!(
+            input_rate > 0 && output_rate > 0,
+            "sample rates must be positive"
+        );
+
+        let divisor = gcd(input_rate as usize, output_rate as usize);
+        let up = output_rate as usize / divisor;
+        let down = input_rate as usize / divisor;
+        let half_width = (taps / 2).max(1);
+
+        // Normalized cutoff relative to the input rate (1.0 = input Nyquist)
+        let cutoff = (up as f64 / down as f64).min(1.0) * PASSBAND;
+
+        let phases = (0..up)
+            .map(|phase| {
+                let frac = phase as f64 / up as f64;
+                let mut taps: Vec<f64> = (0..=2 * half_width)
+                    .map(|k| {
+                        let tau = frac - (k as f64 - half_width as f64);
+                        cutoff * sinc(cutoff * tau) * blackman(tau, half_width as f64 + 1.0)
+                    })
+                    .collect();
+
+                // Normalize each phase to unity gain at DC
+                let sum: f64 = taps.iter().sum();
+                if sum != 0.0 {
+                    for tap in taps.iter_mut() {
+                        *tap /= sum;
+                    }
+                }
+                taps
+            })
+            .collect();
+
+        Resampler {
+            input_rate,
+            output_rate,
+            up,
+            down,
+            half_width,
+            phases,
+        }
+    }
+
+    pub fn input_rate(&self) -> u32 {
+        self.input_rate
+    }
+
+    pub fn output_rate(&self) -> u32 {
+        self.output_rate
+    }
+
+    /// The reduced conversion ratio as (up, down)
+    pub fn ratio(&self) -> (usize, usize) {
+        (self.up, self.down)
+    }
+
+    /// Number of output samples produced for `input_len` input samples
+    pub fn output_len(&self, input_len: usize) -> usize {
+        (input_len * self.up).div_ceil(self.down)
+    }
+
+    /// Resamples a whole signal. Samples outside the input are treated as silence.
+    pub fn process(&self, input: &[f64]) -> Vec<f64> {
+        if self.up == 1 && self.down == 1 {
+            return input.to_vec();
+        }
+
+        (0..self.output_len(input.len()))
+            .map(|out_index| self.interpolate(out_index, input, 0))
+            .collect()
+    }
+
+    /// Turns the resampler into a stream that accepts the signal in chunks
+    pub fn into_stream(self) -> ResamplerStream {
+        ResamplerStream {
+            resampler: self,
+            buffer: Vec::new(),
+            buffer_start: 0,
+            received: 0,
+            next_output: 0,
+        }
+    }
+
+    // Input index of the center tap for an output sample
+    fn center(&self, out_index: usize) -> usize {
+        out_index * self.down / self.up
+    }
+
+    // Computes output `out_index` from `input`, which holds the input samples starting at
+    // absolute index `input_start`; anything outside it counts as silence
+    fn interpolate(&self, out_index: usize, input: &[f64], input_start: usize) -> f64 {
+        let position = out_index * self.down;
+        let center = (position / self.up) as isize - input_start as isize;
+        let taps = &self.phases[position % self.up];
+        let half_width = self.half_width as isize;
+
+        // Input samples center - half_width ..= center + half_width, clipped to the signal
+        let first = center - half_width;
+        let start = first.max(0);
+        let end = (center + half_width + 1).min(input.len() as isize);
+        if start >= end {
+            return 0.0;
+        }
+
+        dot(
+            &input[start as usize..end as usize],
+            &taps[(start - first) as usize..],
+        )
+    }
+}
+
+/// Incremental form of `Resampler::process`: feeding the signal in chunks of any size and then
+/// calling `finish` produces exactly the same samples, while only keeping the input samples
+/// the filter still needs
+#[derive(Debug, Clone)]
+pub struct ResamplerStream {
+    resampler: Resampler,
+    buffer: Vec<f64>,
+    // Absolute index of buffer[0]
+    buffer_start: usize,
+    received: usize,
+    next_output: usize,
+}
+
+impl ResamplerStream {
+    pub fn resampler(&self) -> &Resampler {
+        &self.resampler
+    }
+
+    /// Appends `input` and writes every output sample whose filter window is now complete
+    pub fn push(&mut self, input: &[f64], output: &mut Vec<f64>) {
+        self.received += input.len();
+        if self.resampler.up == 1 && self.resampler.down == 1 {
+            output.extend_from_slice(input);
+            self.next_output = self.received;
+            return;
+        }
+
+        self.buffer.extend_from_slice(input);
+        while self.resampler.center(self.next_output) + self.resampler.half_width < self.received {
+            output.push(self.resampler.interpolate(
+                self.next_output,
+                &self.buffer,
+                self.buffer_start,
+            ));
+            self.next_output += 1;
+        }
+        self.discard_consumed();
+    }
+
+    /// Writes the remaining output samples, treating the input as ending here
+    pub fn finish(&mut self, output: &mut Vec<f64>) {
+        if self.resampler.up == 1 && self.resampler.down == 1 {
+            return;
+        }
+
+        let total = self.resampler.output_len(self.received);
+        while self.next_output < total {
+            output.push(self.resampler.interpolate(
+                self.next_output,
+                &self.buffer,
+                self.buffer_start,
+            ));
+            self.next_output += 1;
+        }
+        self.buffer.clear();
+        self.buffer_start = self.received;
+    }
+
+    // Drops input samples that no future output can reach
+    fn discard_consumed(&mut self) {
+        let needed_from = self
+            .resampler
+            .center(self.next_output)
+            .saturating_sub(self.resampler.half_width);
+        let drop = needed_from
+            .saturating_sub(self.buffer_start)
+            .min(self.buffer.len());
+        if drop > 0 {
+            self.buffer.drain(..drop);
+            self.buffer_start += drop;
+        }
+    }
+}
+
+fn gcd(mut a: usize, mut b: usize) -> usize {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-12 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+// Blackman window over [-width, width]
+fn blackman(x: f64, width: f64) -> f64 {
+    if x.abs() >= width {
+        return 0.0;
+    }
+    let phase = PI * x / width;
+    0.42 + 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos()
+}
+
+// Dot product written with independent accumulators so the compiler can vectorize it
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    let len = a.len().min(b.len());
+    let (a, b) = (&a[..len], &b[..len]);
+
+    let mut acc = [0.0; 4];
+    let mut a_chunks = a.chunks_exact(4);
+    let mut b_chunks = b.chunks_exact(4);
+    for (x, y) in (&mut a_chunks).zip(&mut b_chunks) {
+        for lane in 0..4 {
+            acc[lane] += x[lane] * y[lane];
+        }
+    }
+
+    let tail: f64 = a_chunks
+        .remainder()
+        .iter()
+        .zip(b_chunks.remainder())
+        .map(|(x, y)| x * y)
+        .sum();
+    (acc[0] + acc[1]) + (acc[2] + acc[3]) + tail
+}
@@ -1,7 +1,14 @@
+use crate::error::{NumeroError, Result};
 use crate::fingerprint::peaks::Peak;
 use plotters::prelude::*;
 use std::path::Path;
 
+impl<E: std::error::Error + Send + Sync> From<DrawingAreaErrorKind<E>> for NumeroError {
+    fn from(e: DrawingAreaErrorKind<E>) -> Self {
+        NumeroError::Plot(e.to_string())
+    }
+}
+
 /// Visualize the kernel in ASCII format
 pub fn visualize_kernel(kernel: &[f64], width: usize) -> String {
     let mut result = String::new();
@@ -39,17 +46,13 @@ pub fn visualize_kernel(kernel: &[f64], width: usize) -> String {
 
     result.push_str("    └");
     result.push_str(&"─".repeat(width));
-    result.push_str("\n");
+    result.push('\n');
 
     result
 }
 
 /// Plot the kernel using plotters library and save to a file
-pub fn plot_kernel(
-    kernel: &[f64],
-    sample_rate: u32,
-    output_path: impl AsRef<Path>,
-) -> Result<(), Box<dyn std::error::Error>> {
+pub fn plot_kernel(kernel: &[f64], sample_rate: u32, output_path: impl AsRef<Path>) -> Result<()> {
     let root = BitMapBackend::new(output_path.as_ref(), (800, 600)).into_drawing_area();
     root.fill(&WHITE)?;
 
@@ -82,8 +85,8 @@ pub fn plot_kernel(
         5,
         &BLUE,
         &|c, s, st| {
-            return EmptyElement::at(c)    // We want the point to be at (x, y)
-                + Circle::new((0, 0), s, st.filled()); // And a circle at its center
+            EmptyElement::at(c)    // We want the point to be at (x, y)
+                + Circle::new((0, 0), s, st.filled()) // And a circle at its center
         },
     ))?;
 
@@ -115,7 +118,7 @@ pub fn plot_kernel_with_frequency_response(
     kernel: &[f64],
     sample_rate: u32,
     output_path: impl AsRef<Path>,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<()> {
     let root = BitMapBackend::new(output_path.as_ref(), (1000, 800)).into_drawing_area();
     root.fill(&WHITE)?;
 
@@ -203,7 +206,7 @@ pub fn plot_filter_comparison(
     filtered: &[f64],
     sample_rate: u32,
     output_path: impl AsRef<Path>,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<()> {
     let root = BitMapBackend::new(output_path.as_ref(), (1000, 800)).into_drawing_area();
     root.fill(&WHITE)?;
 
@@ -293,7 +296,7 @@ pub fn plot_spectrogram(
     hop_size: usize,
     peaks: &[Peak],
     output_path: impl AsRef<Path>,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<()> {
     let root = BitMapBackend::new(output_path.as_ref(), (1000, 600)).into_drawing_area();
     root.fill(&WHITE)?;
 
@@ -326,53 +329,55 @@ pub fn plot_spectrogram(
     // Create color gradient for the heatmap
     let color_gradient = colorous::VIRIDIS;
 
-    // Calculate pixel dimensions
-    let pixel_width = 900.0 / spectrogram.len() as f64; // Reduced width to accommodate colorbar
-    let pixel_height = 600.0 / frame_size as f64;
-
-    // Draw each time-frequency bin
-    for (t, frame) in spectrogram.iter().enumerate() {
-        for (f, &magnitude) in frame.iter().take(frame_size / 2).rev().enumerate() {
-            // Reverse frequency order
-            let time = t as f64 * hop_size as f64 / sample_rate as f64;
-            let freq = f as f64 * max_freq / (frame_size / 2) as f64;
-
-            // Convert magnitude to dB and normalize
-            let db = 20.0 * (magnitude / max_magnitude).log10();
-            let normalized = ((db + 100.0) / 100.0).max(0.0).min(1.0);
-
-            let color = color_gradient.eval_continuous(normalized);
-            let rgb = RGBColor(color.r, color.g, color.b);
-
-            // Draw rectangle for this time-frequency bin
-            chart.draw_series(std::iter::once(Rectangle::new(
-                [
-                    (time, freq),
-                    (
-                        time + hop_size as f64 / sample_rate as f64,
-                        freq + max_freq / (frame_size / 2) as f64,
-                    ),
-                ],
-                rgb.filled(),
-            )))?;
-        }
-    }
+    // Draw every time-frequency bin in a single series
+    let bins = spectrogram.iter().enumerate().flat_map(|(t, frame)| {
+        frame
+            .iter()
+            .take(frame_size / 2)
+            .rev()
+            .enumerate()
+            .map(move |(f, &magnitude)| {
+                // Reverse frequency order
+                let time = t as f64 * hop_size as f64 / sample_rate as f64;
+                let freq = f as f64 * max_freq / (frame_size / 2) as f64;
+
+                // Convert magnitude to dB and normalize
+                let db = 20.0 * (magnitude / max_magnitude).log10();
+                let normalized = ((db + 100.0) / 100.0).clamp(0.0, 1.0);
+
+                let color = color_gradient.eval_continuous(normalized);
+                let rgb = RGBColor(color.r, color.g, color.b);
+
+                // Rectangle for this time-frequency bin
+                Rectangle::new(
+                    [
+                        (time, freq),
+                        (
+                            time + hop_size as f64 / sample_rate as f64,
+                            freq + max_freq / (frame_size / 2) as f64,
+                        ),
+                    ],
+                    rgb.filled(),
+                )
+            })
+    });
+    chart.draw_series(bins)?;
 
     // Draw peaks as dark blue dots
-    for peak in peaks {
+    chart.draw_series(peaks.iter().map(|peak| {
         let time = peak.frame_index as f64 * hop_size as f64 / sample_rate as f64;
         let freq = peak.freq_bin as f64 * max_freq / (frame_size / 2) as f64;
 
-        // Draw small solid dots
-        chart.draw_series(std::iter::once(Circle::new(
+        // Small solid dots
+        Circle::new(
             (time, freq),
             2,                                               // Reduced size
             ShapeStyle::from(&RGBColor(0, 0, 139)).filled(), // Dark blue filled dots
-        )))?;
-    }
+        )
+    }))?;
 
     // Add colorbar with more width
-    let (main_area, colorbar_area) = root.split_horizontally(920);
+    let (_main_area, colorbar_area) = root.split_horizontally(920);
     let mut colorbar = ChartBuilder::on(&colorbar_area)
         .margin(5)
         .x_label_area_size(0)
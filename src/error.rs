@@ -0,0 +1,77 @@
+// Crate-wide error type
+// Every public fallible function returns `numero::Result`, so callers can match on the
+// failure mode instead of parsing message strings.
+
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum NumeroError {
+    /// Underlying I/O failure (opening, reading or writing a file)
+    Io(io::Error),
+    /// The audio data could not be decoded
+    Decode(String),
+    /// The input has no samples
+    EmptyInput,
+    /// The sample rate is not one the pipeline accepts
+    UnsupportedSampleRate { sample_rate: u32, reason: String },
+    /// The input is too short to produce a single analysis frame
+    TooShort { samples: usize, required: usize },
+    /// The decoded audio failed a sanity check
+    InvalidAudio(String),
+    /// A `FingerprintConfig` failed validation
+    InvalidConfig(String),
+    /// Fingerprints built with different configs were combined
+    ConfigMismatch(String),
+    /// A store file is malformed or of an unexpected kind or version
+    Store(String),
+    /// An index operation failed
+    Index(String),
+    /// A score threshold could not be calibrated
+    Calibration(String),
+    /// Rendering a plot failed
+    Plot(String),
+}
+
+pub type Result<T> = std::result::Result<T, NumeroError>;
+
+impl fmt::Display for NumeroError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NumeroError::Io(e) => write!(f, "I/O error: {}", e),
+            NumeroError::Decode(msg) => write!(f, "Failed to decode audio: {}", msg),
+            NumeroError::EmptyInput => write!(f, "No audio samples found"),
+            NumeroError::UnsupportedSampleRate {
+                sample_rate,
+                reason,
+            } => write!(f, "Unsupported sample rate {} Hz: {}", sample_rate, reason),
+            NumeroError::TooShort { samples, required } => write!(
+                f,
+                "Input too short: {} samples, at least {} required",
+                samples, required
+            ),
+            NumeroError::InvalidAudio(msg) => write!(f, "Invalid audio: {}", msg),
+            NumeroError::InvalidConfig(msg) => write!(f, "Invalid config: {}", msg),
+            NumeroError::ConfigMismatch(msg) => write!(f, "Config mismatch: {}", msg),
+            NumeroError::Store(msg) => write!(f, "Store error: {}", msg),
+            NumeroError::Index(msg) => write!(f, "Index error: {}", msg),
+            NumeroError::Calibration(msg) => write!(f, "Calibration error: {}", msg),
+            NumeroError::Plot(msg) => write!(f, "Plot error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for NumeroError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NumeroError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for NumeroError {
+    fn from(e: io::Error) -> Self {
+        NumeroError::Io(e)
+    }
+}
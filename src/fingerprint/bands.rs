@@ -0,0 +1,125 @@
+// Frequency band layouts
+// Band-maximum peak picking keeps the strongest bin of each band, so the band edges decide how
+// many peaks each part of the spectrum gets. Equal-width bands give most of them to the top
+// octave; the perceptual layouts space the edges evenly on a logarithmic, Bark or mel scale
+// instead, so low and mid frequencies, where most musical energy sits, get their own bands.
+
+use crate::fingerprint::config::{BandLayout, FingerprintConfig, MIN_LOG_BAND_HZ};
+use std::ops::Range;
+
+/// The bin range of every band for the config's layout and frame size
+pub fn band_ranges(config: &FingerprintConfig) -> Vec<Range<usize>> {
+    let num_bins = config.frame_size / 2 + 1;
+    let bin_hz = config.target_sample_rate as f64 / config.frame_size as f64;
+    let nyquist = config.target_sample_rate as f64 / 2.0;
+
+    match &config.band_layout {
+        BandLayout::Linear => linear_bands(num_bins, config.num_bands),
+        BandLayout::Log => scaled_bands(
+            num_bins,
+            config.num_bands,
+            bin_hz,
+            MIN_LOG_BAND_HZ.min(nyquist / 2.0),
+            nyquist,
+            f64::ln,
+            f64::exp,
+        ),
+        BandLayout::Bark => scaled_bands(
+            num_bins,
+            config.num_bands,
+            bin_hz,
+            0.0,
+            nyquist,
+            hz_to_bark,
+            bark_to_hz,
+        ),
+        BandLayout::Mel => scaled_bands(
+            num_bins,
+            config.num_bands,
+            bin_hz,
+            0.0,
+            nyquist,
+            hz_to_mel,
+            mel_to_hz,
+        ),
+        BandLayout::Custom(edges) => custom_bands(num_bins, bin_hz, edges),
+    }
+}
+
+/// Equal-width bands; the last one takes the remainder
+pub fn linear_bands(num_bins: usize, num_bands: usize) -> Vec<Range<usize>> {
+    let band_size = num_bins / num_bands;
+    (0..num_bands)
+        .map(|band| {
+            let start = band * band_size;
+            let end = if band == num_bands - 1 {
+                num_bins
+            } else {
+                start + band_size
+            };
+            start..end
+        })
+        .collect()
+}
+
+pub fn hz_to_mel(hz: f64) -> f64 {
+    2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+pub fn mel_to_hz(mel: f64) -> f64 {
+    700.0 * (10f64.powf(mel / 2595.0) - 1.0)
+}
+
+/// Traunmüller's approximation of the Bark scale
+pub fn hz_to_bark(hz: f64) -> f64 {
+    26.81 * hz / (1960.0 + hz) - 0.53
+}
+
+pub fn bark_to_hz(bark: f64) -> f64 {
+    1960.0 * (bark + 0.53) / (26.28 - bark)
+}
+
+// Bands whose edges are evenly spaced on the scale given by `to_scale`/`from_scale`.
+// The first band always starts at bin 0 and the last ends at the top bin; every band keeps at
+// least one bin even where the scale is finer than the bin spacing.
+fn scaled_bands(
+    num_bins: usize,
+    num_bands: usize,
+    bin_hz: f64,
+    low_hz: f64,
+    high_hz: f64,
+    to_scale: fn(f64) -> f64,
+    from_scale: fn(f64) -> f64,
+) -> Vec<Range<usize>> {
+    let low = to_scale(low_hz);
+    let high = to_scale(high_hz);
+
+    let mut edges = vec![0; num_bands + 1];
+    edges[num_bands] = num_bins;
+    for i in 1..num_bands {
+        let hz = from_scale(low + (high - low) * i as f64 / num_bands as f64);
+        let bin = (hz / bin_hz).round() as usize;
+        edges[i] = bin.max(edges[i - 1] + 1).min(num_bins - (num_bands - i));
+    }
+
+    edges.windows(2).map(|w| w[0]..w[1]).collect()
+}
+
+// Each band holds the bins whose center frequency lies in [lower edge, upper edge),
+// the last band also including its upper edge
+fn custom_bands(num_bins: usize, bin_hz: f64, edges: &[f64]) -> Vec<Range<usize>> {
+    let last = edges.len().saturating_sub(2);
+    edges
+        .windows(2)
+        .enumerate()
+        .map(|(i, w)| {
+            let start = ((w[0] / bin_hz).ceil() as usize).min(num_bins);
+            let end = if i == last {
+                (w[1] / bin_hz).floor() as usize + 1
+            } else {
+                (w[1] / bin_hz).ceil() as usize
+            };
+            start..end.min(num_bins).max(start)
+        })
+        .collect()
+}
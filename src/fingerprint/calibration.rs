@@ -0,0 +1,77 @@
+// Match thresholds
+// A significance score says how far an alignment stands out from chance, but what counts as
+// "far enough" depends on the catalogue: a bigger index gives a clip more tracks to collide
+// with by accident. `Calibration` picks the threshold from the scores that known non-matching
+// clips actually reach, so that no more than the requested fraction of them would be accepted.
+
+use crate::error::{NumeroError, Result};
+
+/// A significance threshold calibrated against known negatives
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    /// Scores strictly above this are accepted
+    pub threshold: f64,
+    /// The false-positive rate the threshold was chosen for
+    pub target_false_positive_rate: f64,
+    /// Fraction of the negatives the threshold accepts, at most the target
+    pub false_positive_rate: f64,
+    /// Number of negative scores the threshold was chosen from
+    pub negatives: usize,
+}
+
+impl Calibration {
+    /// Picks the lowest threshold that accepts at most `target_false_positive_rate` of the
+    /// negative scores. The rate must be below 1 and at least one negative is needed; with
+    /// fewer than 1 / rate negatives, the threshold is the highest negative score.
+    pub fn from_negative_scores(
+        negative_scores: &[f64],
+        target_false_positive_rate: f64,
+    ) -> Result<Self> {
+        if !(0.0..1.0).contains(&target_false_positive_rate) {
+            return Err(NumeroError::Calibration(format!(
+                "false-positive rate must be at least 0 and below 1, got {}",
+                target_false_positive_rate
+            )));
+        }
+        if negative_scores.is_empty() {
+            return Err(NumeroError::Calibration(
+                "no negative scores to calibrate against".to_string(),
+            ));
+        }
+        if negative_scores.iter().any(|score| score.is_nan()) {
+            return Err(NumeroError::Calibration(
+                "negative scores must be numbers".to_string(),
+            ));
+        }
+
+        let mut sorted = negative_scores.to_vec();
+        sorted.sort_by(|a, b| b.total_cmp(a));
+        // Accepting only scores above the (k+1)-th highest lets through at most k negatives
+        let allowed = (target_false_positive_rate * sorted.len() as f64).floor() as usize;
+        let threshold = sorted[allowed.min(sorted.len() - 1)];
+
+        Ok(Calibration {
+            threshold,
+            target_false_positive_rate,
+            false_positive_rate: false_positive_rate(negative_scores, threshold),
+            negatives: negative_scores.len(),
+        })
+    }
+
+    /// Whether a score clears the threshold
+    pub fn accepts(&self, score: f64) -> bool {
+        score > self.threshold
+    }
+}
+
+/// Fraction of `negative_scores` a threshold would accept
+pub fn false_positive_rate(negative_scores: &[f64], threshold: f64) -> f64 {
+    if negative_scores.is_empty() {
+        return 0.0;
+    }
+    let accepted = negative_scores
+        .iter()
+        .filter(|&&score| score > threshold)
+        .count();
+    accepted as f64 / negative_scores.len() as f64
+}
@@ -0,0 +1,369 @@
+// Fingerprinting parameters
+// Every tunable of the pipeline lives in one struct so experiments can change them at runtime.
+// A config is validated before use and travels with the fingerprints it produced, since hashes
+// built with different parameters cannot be compared.
+
+use crate::error::{NumeroError, Result};
+use crate::fingerprint::bands::band_ranges;
+use crate::fingerprint::hash::HashCodec;
+use crate::fingerprint::timebase::TimeBase;
+
+/// Names accepted by `FingerprintConfig::preset`
+pub const PRESETS: [&str; 3] = ["music", "speech", "broadcast"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FingerprintConfig {
+    /// Rate the signal is downsampled to before analysis (Hz)
+    pub target_sample_rate: u32,
+    /// Length of the resampling/anti-aliasing filter in input samples (must be odd)
+    pub filter_taps: usize,
+    /// Samples per frame, a power of two
+    pub frame_size: usize,
+    /// Hop size for overlapping frames
+    pub hop_size: usize,
+    /// Number of frequency bands for peak detection
+    pub num_bands: usize,
+    /// Maximum frame difference for pairing peaks
+    pub target_zone_frames: usize,
+    /// Threshold multiplier for peak detection
+    pub threshold_multiplier: f64,
+    /// How signal level is factored out before peak picking
+    pub normalization: Normalization,
+    /// How peaks are selected from the spectrogram
+    pub peak_picker: PeakPicker,
+    /// How the spectrum is split into bands for `PeakPicker::BandMax`
+    pub band_layout: BandLayout,
+    /// Bit layout of the peak-pair hashes
+    pub hash_codec: HashCodec,
+    /// Which peaks in an anchor's target zone it is paired with
+    pub pairing: Pairing,
+    /// How an anchor and its targets are turned into hashes
+    pub hash_family: HashFamily,
+}
+
+/// Hash families.
+/// Pair hashes are the most selective but change when a recording is played faster or
+/// transposed; triplet hashes trade some selectivity for surviving both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashFamily {
+    /// Anchor and target frequency and their frame difference, packed by `hash_codec`
+    #[default]
+    Pair,
+    /// Anchor and two targets: the frequency ratios of the targets to the anchor and the ratio
+    /// of their frame differences (see `fingerprint::triplet`)
+    Triplet,
+}
+
+impl HashFamily {
+    pub fn name(self) -> &'static str {
+        match self {
+            HashFamily::Pair => "pair",
+            HashFamily::Triplet => "triplet",
+        }
+    }
+}
+
+/// Limits on how many hashes each anchor peak produces.
+/// The default pairs an anchor with every later peak of its target zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Pairing {
+    /// Most targets per anchor, or unlimited
+    pub max_fan_out: Option<usize>,
+    /// Smallest frame difference between anchor and target
+    pub min_dt: usize,
+    /// Largest distance in bins between anchor and target frequency, or unlimited
+    pub max_freq_distance: Option<usize>,
+    /// With a fan-out limit, keep the strongest targets rather than the earliest
+    pub strongest_first: bool,
+}
+
+/// Band layouts for band-maximum peak picking
+#[derive(Debug, Clone, PartialEq)]
+pub enum BandLayout {
+    /// `num_bands` bands of equal width in Hz
+    Linear,
+    /// `num_bands` bands of equal width in octaves, starting at `MIN_LOG_BAND_HZ`
+    Log,
+    /// `num_bands` bands of equal width on the Bark scale
+    Bark,
+    /// `num_bands` bands of equal width on the mel scale
+    Mel,
+    /// Bands between consecutive edges in Hz; `num_bands` is ignored
+    Custom(Vec<f64>),
+}
+
+/// Lower edge of the first logarithmic band; everything below it joins that band
+pub const MIN_LOG_BAND_HZ: f64 = 50.0;
+
+impl BandLayout {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BandLayout::Linear => "linear",
+            BandLayout::Log => "log",
+            BandLayout::Bark => "bark",
+            BandLayout::Mel => "mel",
+            BandLayout::Custom(_) => "custom",
+        }
+    }
+}
+
+/// Peak selection strategies
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PeakPicker {
+    /// The strongest bin of each of `num_bands` bands in every frame
+    BandMax,
+    /// Bins that are the maximum of the surrounding `±neighborhood_frames` × `±neighborhood_bins`
+    /// region, keeping only the strongest `peaks_per_second` in each second of audio
+    Constellation {
+        neighborhood_frames: usize,
+        neighborhood_bins: usize,
+        peaks_per_second: f64,
+    },
+}
+
+impl PeakPicker {
+    /// A constellation picker with settings that suit the music preset
+    pub fn constellation() -> Self {
+        PeakPicker::Constellation {
+            neighborhood_frames: 3,
+            neighborhood_bins: 8,
+            peaks_per_second: 30.0,
+        }
+    }
+}
+
+/// Gain normalization strategies.
+/// `PerFrame` and `LogMagnitude` work on the spectrogram one frame at a time, so they give the
+/// same result for a clip as for the same stretch of the full recording. `Rms`, the default of
+/// every preset, also looks at the second before each frame, so a clip only agrees with the
+/// recording from one second in. All but `Peak` can be used when streaming.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// Samples are used at i16 full scale
+    None,
+    /// Samples are divided by the largest absolute sample of the whole signal
+    Peak,
+    /// Each spectrum is divided by the RMS magnitude of the frames in the last second
+    Rms,
+    /// Each spectrum is divided by its own RMS magnitude
+    PerFrame,
+    /// Each magnitude is divided by its frame's mean, then compressed with ln(1 + x)
+    LogMagnitude,
+}
+
+impl Normalization {
+    /// Every strategy
+    pub const ALL: [Normalization; 5] = [
+        Normalization::None,
+        Normalization::Peak,
+        Normalization::Rms,
+        Normalization::PerFrame,
+        Normalization::LogMagnitude,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Normalization::None => "none",
+            Normalization::Peak => "peak",
+            Normalization::Rms => "rms",
+            Normalization::PerFrame => "per-frame",
+            Normalization::LogMagnitude => "log-magnitude",
+        }
+    }
+
+    /// Looks up a strategy by its `name`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|n| n.name() == name)
+    }
+}
+
+impl Default for FingerprintConfig {
+    fn default() -> Self {
+        Self::music()
+    }
+}
+
+impl FingerprintConfig {
+    /// General purpose settings for recorded music
+    pub fn music() -> Self {
+        let frame_size = 1024;
+        let target_zone_frames = 20;
+        FingerprintConfig {
+            target_sample_rate: 11025,
+            filter_taps: 101,
+            frame_size,
+            hop_size: 512,
+            num_bands: 6,
+            target_zone_frames,
+            threshold_multiplier: 0.1,
+            normalization: Normalization::Rms,
+            peak_picker: PeakPicker::BandMax,
+            band_layout: BandLayout::Linear,
+            hash_codec: HashCodec::fitted(frame_size / 2 + 1, target_zone_frames, 1),
+            pairing: Pairing::default(),
+            hash_family: HashFamily::Pair,
+        }
+    }
+
+    /// Narrow-band settings for voice: most speech energy sits below 4 kHz
+    pub fn speech() -> Self {
+        let frame_size = 512;
+        let target_zone_frames = 15;
+        FingerprintConfig {
+            target_sample_rate: 8000,
+            filter_taps: 101,
+            frame_size,
+            hop_size: 256,
+            num_bands: 4,
+            target_zone_frames,
+            threshold_multiplier: 0.2,
+            normalization: Normalization::Rms,
+            peak_picker: PeakPicker::BandMax,
+            band_layout: BandLayout::Linear,
+            hash_codec: HashCodec::fitted(frame_size / 2 + 1, target_zone_frames, 1),
+            pairing: Pairing::default(),
+            hash_family: HashFamily::Pair,
+        }
+    }
+
+    /// Denser, more noise tolerant settings for radio and TV captures
+    pub fn broadcast() -> Self {
+        let frame_size = 1024;
+        let target_zone_frames = 30;
+        FingerprintConfig {
+            target_sample_rate: 11025,
+            filter_taps: 101,
+            frame_size,
+            hop_size: 256,
+            num_bands: 6,
+            target_zone_frames,
+            threshold_multiplier: 0.2,
+            normalization: Normalization::Rms,
+            peak_picker: PeakPicker::BandMax,
+            band_layout: BandLayout::Linear,
+            hash_codec: HashCodec::fitted(frame_size / 2 + 1, target_zone_frames, 1),
+            pairing: Pairing::default(),
+            hash_family: HashFamily::Pair,
+        }
+    }
+
+    /// Looks up a preset by name (see `PRESETS`)
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "music" => Some(Self::music()),
+            "speech" => Some(Self::speech()),
+            "broadcast" => Some(Self::broadcast()),
+            _ => None,
+        }
+    }
+
+    /// How frame indices produced with this config convert to seconds
+    pub fn time_base(&self) -> TimeBase {
+        TimeBase::from_config(self)
+    }
+
+    /// Checks that the parameters describe a usable pipeline
+    pub fn validate(&self) -> Result<()> {
+        if self.target_sample_rate == 0 {
+            return Err(NumeroError::InvalidConfig(
+                "target_sample_rate must be positive".to_string(),
+            ));
+        }
+        if self.filter_taps < 3 || self.filter_taps.is_multiple_of(2) {
+            return Err(NumeroError::InvalidConfig(format!(
+                "filter_taps must be odd and at least 3, got {}",
+                self.filter_taps
+            )));
+        }
+        if self.frame_size < 2 || !self.frame_size.is_power_of_two() {
+            return Err(NumeroError::InvalidConfig(format!(
+                "frame_size must be a power of two of at least 2, got {}",
+                self.frame_size
+            )));
+        }
+        if self.hop_size == 0 || self.hop_size > self.frame_size {
+            return Err(NumeroError::InvalidConfig(format!(
+                "hop_size must be between 1 and frame_size ({}), got {}",
+                self.frame_size, self.hop_size
+            )));
+        }
+        let num_bins = self.frame_size / 2 + 1;
+        if self.num_bands == 0 || self.num_bands > num_bins {
+            return Err(NumeroError::InvalidConfig(format!(
+                "num_bands must be between 1 and {} for frame_size {}, got {}",
+                num_bins, self.frame_size, self.num_bands
+            )));
+        }
+        if self.target_zone_frames == 0 {
+            return Err(NumeroError::InvalidConfig(
+                "target_zone_frames must be at least 1".to_string(),
+            ));
+        }
+        self.hash_codec.validate(self.target_zone_frames)?;
+        if (self.hash_codec.max_freq_bin() as usize) < num_bins - 1 {
+            return Err(NumeroError::InvalidConfig(format!(
+                "hash layout holds bins up to {} but frame_size {} has bins up to {}; refit it with HashCodec::fitted",
+                self.hash_codec.max_freq_bin(),
+                self.frame_size,
+                num_bins - 1
+            )));
+        }
+        if self.pairing.max_fan_out == Some(0) {
+            return Err(NumeroError::InvalidConfig(
+                "pairing max_fan_out must be at least 1".to_string(),
+            ));
+        }
+        if self.pairing.min_dt > self.target_zone_frames {
+            return Err(NumeroError::InvalidConfig(format!(
+                "pairing min_dt {} is beyond the target zone of {} frames",
+                self.pairing.min_dt, self.target_zone_frames
+            )));
+        }
+        if !self.threshold_multiplier.is_finite() || self.threshold_multiplier < 0.0 {
+            return Err(NumeroError::InvalidConfig(format!(
+                "threshold_multiplier must be a non-negative number, got {}",
+                self.threshold_multiplier
+            )));
+        }
+        if let BandLayout::Custom(edges) = &self.band_layout {
+            let nyquist = self.target_sample_rate as f64 / 2.0;
+            if edges.len() < 2
+                || edges.iter().any(|&hz| !(0.0..=nyquist).contains(&hz))
+                || edges.windows(2).any(|w| w[0] >= w[1])
+            {
+                return Err(NumeroError::InvalidConfig(format!(
+                    "custom band edges must be at least two increasing frequencies between 0 and {} Hz, got {:?}",
+                    nyquist, edges
+                )));
+            }
+            if band_ranges(self).iter().any(|band| band.is_empty()) {
+                return Err(NumeroError::InvalidConfig(format!(
+                    "custom band edges {:?} leave a band without any frequency bin",
+                    edges
+                )));
+            }
+        }
+        if let PeakPicker::Constellation {
+            neighborhood_frames,
+            neighborhood_bins,
+            peaks_per_second,
+        } = self.peak_picker
+        {
+            if neighborhood_frames == 0 || neighborhood_bins == 0 || neighborhood_bins >= num_bins {
+                return Err(NumeroError::InvalidConfig(format!(
+                    "constellation neighborhood must be at least 1 frame and between 1 and {} bins, got {} frames and {} bins",
+                    num_bins - 1,
+                    neighborhood_frames,
+                    neighborhood_bins
+                )));
+            }
+            if !peaks_per_second.is_finite() || peaks_per_second <= 0.0 {
+                return Err(NumeroError::InvalidConfig(format!(
+                    "peaks_per_second must be a positive number, got {}",
+                    peaks_per_second
+                )));
+            }
+        }
+        Ok(())
+    }
+}
@@ -2,39 +2,108 @@
 // Normalization
 // Inside this function (in fingerprint.go), the raw int16 samples are converted into float64 values scaled between –1 and 1:
 
-use crate::dsp::filter::{apply_fir_filter, generate_low_pass_kernel};
+use crate::dsp::fft::{SpectrumAnalyzer, SpectrumScale};
+use crate::dsp::resample::Resampler;
+#[cfg(feature = "plot")]
 use crate::dsp::viz::plot_spectrogram;
-use crate::fingerprint::hash::hash_fingerprint;
-use crate::fingerprint::peaks::detect_peaks;
-use crate::fingerprint::spectogram::compute_spectrogram;
-use crate::fingerprint::utils::{frame_signal, hamming_window};
-
-const TARGET_SAMPLE_RATE: u32 = 11025; // Downsampled rate.
-const FILTER_TAPS: usize = 101; // Samples per frame
-const FRAME_SIZE: usize = 1024; // Samples per frame
-const HOP_SIZE: usize = 512; // Hop size for overlapping frames
-const NUM_BANDS: usize = 6; // Number of frequency bands for peak detection
-const TARGET_ZONE_FRAMES: usize = 20; // Maximum frame difference for pairing peaks
-const THRESHOLD_MULTIPLIER: f64 = 0.1; // Threshold multiplier for peak detection
-
-pub fn finger_print(samples: &[i16], sample_rate: u32) -> Result<Vec<u32>, String> {
+use crate::error::{NumeroError, Result};
+use crate::fingerprint::config::{FingerprintConfig, Normalization};
+use crate::fingerprint::hash::{hash_fingerprint, Fingerprint};
+use crate::fingerprint::normalize::SpectrumNormalizer;
+use crate::fingerprint::peaks::{Peak, PeakDetector};
+use crate::fingerprint::timebase::TimeBase;
+use crate::fingerprint::utils::hamming_window;
+#[cfg(feature = "plot")]
+use std::path::Path;
+
+/// The fingerprints of one signal together with the config that produced them
+#[derive(Debug, Clone, PartialEq)]
+pub struct FingerprintSet {
+    pub config: FingerprintConfig,
+    pub fingerprints: Vec<Fingerprint>,
+}
+
+impl FingerprintSet {
+    pub fn len(&self) -> usize {
+        self.fingerprints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fingerprints.is_empty()
+    }
+
+    /// How this set's anchor frames convert to seconds
+    pub fn time_base(&self) -> TimeBase {
+        self.config.time_base()
+    }
+}
+
+/// Intermediate results of the pipeline, before peaks are paired into hashes.
+/// Returned by `analyze` for diagnostics; `finger_print` itself never touches the filesystem.
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    pub spectrogram: Vec<Vec<f64>>,
+    pub peaks: Vec<Peak>,
+}
+
+impl Analysis {
+    /// Renders the spectrogram with its peaks to a PNG file
+    #[cfg(feature = "plot")]
+    pub fn plot(&self, config: &FingerprintConfig, output_path: impl AsRef<Path>) -> Result<()> {
+        plot_spectrogram(
+            &self.spectrogram,
+            config.target_sample_rate,
+            config.frame_size,
+            config.hop_size,
+            &self.peaks,
+            output_path,
+        )
+    }
+}
+
+pub fn finger_print(
+    samples: &[i16],
+    sample_rate: u32,
+    config: &FingerprintConfig,
+) -> Result<FingerprintSet> {
+    let Analysis { peaks, .. } = analyze(samples, sample_rate, config)?;
+
+    // Generate and return the fingerprint hashes
+    let fingerprints = hash_fingerprint(&peaks, config);
+    Ok(FingerprintSet {
+        config: config.clone(),
+        fingerprints,
+    })
+}
+
+/// Runs the pipeline up to peak detection: normalize, filter, downsample, frame,
+/// compute the spectrogram and pick its peaks
+pub fn analyze(samples: &[i16], sample_rate: u32, config: &FingerprintConfig) -> Result<Analysis> {
+    config.validate()?;
+
     // Check if samples are empty or sample rate is lower that the target sample rate
-    if samples.is_empty() || sample_rate < TARGET_SAMPLE_RATE {
-        return Err("Invalid input: samples are empty or sample rate is too low".to_string());
+    if samples.is_empty() {
+        return Err(NumeroError::EmptyInput);
     }
+    check_sample_rate(sample_rate, config)?;
 
-    // Find the maximum absolute value of the samples, handling i16::MIN specially
-    let max_abs = samples
-        .iter()
-        .map(|&s| {
-            if s == i16::MIN {
-                32768_i32
-            } else {
-                s.abs() as i32
-            }
-        })
-        .max()
-        .unwrap_or(1) as f64;
+    // Only peak normalization looks at the whole signal; everything else is
+    // applied to the spectrogram below
+    let max_abs = match config.normalization {
+        // Find the maximum absolute value of the samples, handling i16::MIN specially
+        Normalization::Peak => samples
+            .iter()
+            .map(|&s| {
+                if s == i16::MIN {
+                    32768_i32
+                } else {
+                    s.abs() as i32
+                }
+            })
+            .max()
+            .unwrap_or(1) as f64,
+        _ => 32768.0,
+    };
 
     // Convert the raw i16 samples to f64 values scaled between -1.0 and 1.0
     let normalized_samples: Vec<f64> = samples
@@ -48,52 +117,51 @@ pub fn finger_print(samples: &[i16], sample_rate: u32) -> Result<Vec<u32>, Strin
         })
         .collect();
 
-    // Get the cutoff frequency for downsampling
-    let cutoff_freq = TARGET_SAMPLE_RATE as f64 / 2.0;
+    // Resample to exactly the target rate; the resampler's filter also removes
+    // everything above the target Nyquist frequency
+    let resampler = Resampler::new(sample_rate, config.target_sample_rate, config.filter_taps);
+    let downsampled = resampler.process(&normalized_samples);
 
-    // Generate the low-pass filter
-    let kernel = generate_low_pass_kernel(cutoff_freq, sample_rate, FILTER_TAPS);
-
-    // Apply the filter
-    let filtered = apply_fir_filter(&normalized_samples, &kernel);
-
-    // Downsample the filtered signal
-    let decimation_factor = sample_rate as f64 / TARGET_SAMPLE_RATE as f64;
-    let mut downsampled = vec![0.0; filtered.len() / decimation_factor as usize];
-    for i in 0..downsampled.len() {
-        downsampled[i] = filtered[i * decimation_factor as usize];
+    if downsampled.len() < config.frame_size {
+        let (up, down) = resampler.ratio();
+        return Err(NumeroError::TooShort {
+            samples: samples.len(),
+            required: (config.frame_size * down).div_ceil(up),
+        });
     }
 
-    // Framing the Signal
-    let frames = frame_signal(&downsampled, FRAME_SIZE, HOP_SIZE);
-
-    // Windowing
-    let window = hamming_window(FRAME_SIZE);
+    // Frame, window and transform the signal in one pass
+    let analyzer = SpectrumAnalyzer::new(config.frame_size, SpectrumScale::Magnitude)
+        .with_window(hamming_window(config.frame_size));
+    let mut spectrogram = analyzer.spectrogram(&downsampled, config.hop_size);
 
-    // Compute the spectrogram
-    let spectrogram = compute_spectrogram(
-        frames
-            .iter()
-            .map(|f| f.iter().map(|&x| x as f32).collect())
-            .collect(),
-        window.iter().map(|&x| x as f32).collect(),
-    );
+    // Factor out the signal level, frame by frame in time order
+    let mut normalizer = SpectrumNormalizer::new(config);
+    for spectrum in spectrogram.iter_mut() {
+        normalizer.apply(spectrum);
+    }
 
     // Detect Peaks
-    let peaks = detect_peaks(&spectrogram, NUM_BANDS, THRESHOLD_MULTIPLIER);
-
-    if let Err(e) = plot_spectrogram(
-        &spectrogram,
-        TARGET_SAMPLE_RATE,
-        FRAME_SIZE,
-        HOP_SIZE,
-        &peaks,
-        "spectrogram.png",
-    ) {
-        eprintln!("Warning: Failed to plot spectrogram: {}", e);
+    let mut detector = PeakDetector::new(config);
+    let mut peaks = Vec::new();
+    for spectrum in &spectrogram {
+        detector.push_frame(spectrum, &mut peaks);
     }
+    detector.finish(&mut peaks);
 
-    // Generate and return the fingerprint hashes
-    let hashes = hash_fingerprint(&peaks, TARGET_ZONE_FRAMES);
-    Ok(hashes)
+    Ok(Analysis { spectrogram, peaks })
+}
+
+/// The pipeline only downsamples, so the input must be at least the target rate
+pub(crate) fn check_sample_rate(sample_rate: u32, config: &FingerprintConfig) -> Result<()> {
+    if sample_rate < config.target_sample_rate {
+        return Err(NumeroError::UnsupportedSampleRate {
+            sample_rate,
+            reason: format!(
+                "below the target sample rate of {} Hz",
+                config.target_sample_rate
+            ),
+        });
+    }
+    Ok(())
 }
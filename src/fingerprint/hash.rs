@@ -1,42 +1,213 @@
+use super::config::{FingerprintConfig, HashFamily};
 use super::peaks::Peak;
+use super::triplet::triplet_anchor;
+use crate::error::{NumeroError, Result};
+
+/// A single fingerprint record: a peak-pair hash and the frame of its anchor peak
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    pub hash: u32,
+    pub anchor_frame: usize,
+}
 
 // HashFingerprint creates 32-bit hashes from pairs of audio peaks.
-// Each hash combines:
-// - 9 bits: anchor frequency
-// - 9 bits: target frequency
-// - 14 bits: time delta between peaks
-pub fn hash_fingerprint(peaks: &[Peak], target_zone: usize) -> Vec<u32> {
+// Each hash combines, from the most significant bits down (see `HashCodec`):
+// - anchor frequency
+// - target frequency
+// - time delta between peaks
+// The anchor peak's frame index is kept alongside each hash so matchers can
+// align on real peak times rather than on positions in the hash list.
+// Which later peaks an anchor is paired with is controlled by the config's `Pairing`; with
+// `HashFamily::Triplet` the same targets are combined two at a time instead.
+pub fn hash_fingerprint(peaks: &[Peak], config: &FingerprintConfig) -> Vec<Fingerprint> {
     let mut hashes = Vec::new();
-    for i in 0..peaks.len() {
-        let anchor = &peaks[i];
-        for j in (i + 1)..peaks.len() {
-            let target = &peaks[j];
-            let dt = target.frame_index as isize - anchor.frame_index as isize;
-
-            if dt < 0 {
-                continue;
-            }
-            if dt > target_zone as isize {
-                break;
+    for (i, anchor) in peaks.iter().enumerate() {
+        hash_anchor(anchor, &peaks[i + 1..], config, &mut hashes);
+    }
+    hashes
+}
+
+/// Hashes one anchor with the peaks that follow it, stopping at the end of its target zone
+pub(crate) fn hash_anchor<'a>(
+    anchor: &Peak,
+    targets: impl IntoIterator<Item = &'a Peak>,
+    config: &FingerprintConfig,
+    hashes: &mut Vec<Fingerprint>,
+) {
+    let targets = select_targets(anchor, targets, config);
+    match config.hash_family {
+        HashFamily::Pair => {
+            for (target, dt) in targets {
+                hashes.push(Fingerprint {
+                    hash: config.hash_codec.encode(
+                        anchor.freq_bin as u32,
+                        target.freq_bin as u32,
+                        dt as u32,
+                    ),
+                    anchor_frame: anchor.frame_index,
+                });
             }
+        }
+        HashFamily::Triplet => {
+            hashes.extend(
+                triplet_anchor(anchor, &targets)
+                    .into_iter()
+                    .map(Fingerprint::from),
+            );
+        }
+    }
+}
 
-            let mut f1 = anchor.freq_bin as u32;
-            let mut f2 = target.freq_bin as u32;
-            let mut dt_u = dt as u32;
+/// The targets an anchor is hashed with and their frame differences, in time order
+pub(crate) fn select_targets<'a>(
+    anchor: &Peak,
+    targets: impl IntoIterator<Item = &'a Peak>,
+    config: &FingerprintConfig,
+) -> Vec<(&'a Peak, usize)> {
+    let pairing = &config.pairing;
 
-            if f1 > 0x1FF {
-                f1 = 0x1FF;
-            }
-            if f2 > 0x1FF {
-                f2 = 0x1FF;
-            }
-            if dt_u > 0x3FFF {
-                dt_u = 0x3FFF;
-            }
+    let in_zone = targets
+        .into_iter()
+        .map(|target| {
+            (
+                target,
+                target.frame_index as isize - anchor.frame_index as isize,
+            )
+        })
+        .filter(|&(_, dt)| dt >= 0)
+        .take_while(|&(_, dt)| dt <= config.target_zone_frames as isize)
+        .map(|(target, dt)| (target, dt as usize))
+        .filter(|&(target, dt)| {
+            dt >= pairing.min_dt
+                && pairing
+                    .max_freq_distance
+                    .is_none_or(|max| target.freq_bin.abs_diff(anchor.freq_bin) <= max)
+        });
 
-            let hash = (f1 << 23) | (f2 << 14) | dt_u;
-            hashes.push(hash);
+    match pairing.max_fan_out {
+        None => in_zone.collect(),
+        Some(fan_out) if !pairing.strongest_first => in_zone.take(fan_out).collect(),
+        Some(fan_out) => {
+            // Pick the strongest targets, then restore time order
+            let mut chosen: Vec<(usize, (&Peak, usize))> = in_zone.enumerate().collect();
+            chosen.sort_by(|a, b| {
+                b.1 .0
+                    .magnitude
+                    .total_cmp(&a.1 .0.magnitude)
+                    .then(a.0.cmp(&b.0))
+            });
+            chosen.truncate(fan_out);
+            chosen.sort_by_key(|&(order, _)| order);
+            chosen.into_iter().map(|(_, target)| target).collect()
         }
     }
-    hashes
+}
+
+/// Bit layout of a peak-pair hash.
+/// Frequencies are divided by `freq_step` before packing, so neighbouring bins can share a
+/// value and a slightly detuned copy still produces the same hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashCodec {
+    /// Bits for each of the two quantized frequencies
+    pub freq_bits: u32,
+    /// Bits for the frame difference
+    pub dt_bits: u32,
+    /// Frequency bins per quantization step
+    pub freq_step: u32,
+}
+
+impl HashCodec {
+    /// The original 9/9/14 layout, which clamps bins above 511
+    pub const LEGACY: HashCodec = HashCodec {
+        freq_bits: 9,
+        dt_bits: 14,
+        freq_step: 1,
+    };
+
+    /// The smallest layout that holds every bin of a `num_bins` spectrum after quantization,
+    /// and every frame difference up to `target_zone`
+    pub fn fitted(num_bins: usize, target_zone: usize, freq_step: u32) -> Self {
+        let max_quantized = (num_bins.saturating_sub(1) as u32) / freq_step.max(1);
+        HashCodec {
+            freq_bits: bits_for(max_quantized),
+            dt_bits: bits_for(target_zone as u32),
+            freq_step: freq_step.max(1),
+        }
+    }
+
+    /// Total number of bits used, saturating for layouts far too wide to be valid
+    pub fn hash_bits(&self) -> u32 {
+        self.freq_bits
+            .saturating_mul(2)
+            .saturating_add(self.dt_bits)
+    }
+
+    /// Largest frequency bin that is encoded without clamping
+    pub fn max_freq_bin(&self) -> u32 {
+        mask(self.freq_bits)
+            .saturating_mul(self.freq_step)
+            .saturating_add(self.freq_step.saturating_sub(1))
+    }
+
+    /// Largest frame difference that is encoded without clamping
+    pub fn max_dt(&self) -> u32 {
+        mask(self.dt_bits)
+    }
+
+    /// Checks that the layout fits in 32 bits and can hold every pair of a target zone
+    pub fn validate(&self, target_zone: usize) -> Result<()> {
+        if self.freq_bits == 0 || self.dt_bits == 0 || self.hash_bits() > 32 {
+            return Err(NumeroError::InvalidConfig(format!(
+                "hash layout needs at least one bit per field and at most 32 in total, got {} + {} + {}",
+                self.freq_bits, self.freq_bits, self.dt_bits
+            )));
+        }
+        if self.freq_step == 0 {
+            return Err(NumeroError::InvalidConfig(
+                "hash freq_step must be positive".to_string(),
+            ));
+        }
+        if target_zone as u64 > self.max_dt() as u64 {
+            return Err(NumeroError::InvalidConfig(format!(
+                "target_zone_frames {} does not fit in {} hash bits (max {})",
+                target_zone,
+                self.dt_bits,
+                self.max_dt()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Pack an anchor frequency, target frequency and time delta into a hash.
+    /// Values that do not fit their bit field are clamped to its maximum.
+    pub fn encode(&self, f1: u32, f2: u32, dt: u32) -> u32 {
+        let f1 = (f1 / self.freq_step).min(mask(self.freq_bits));
+        let f2 = (f2 / self.freq_step).min(mask(self.freq_bits));
+        let dt = dt.min(mask(self.dt_bits));
+
+        (f1 << (self.freq_bits + self.dt_bits)) | (f2 << self.dt_bits) | dt
+    }
+
+    /// Extract components from a hash. Frequencies come back as the lowest bin of their
+    /// quantization step.
+    pub fn decode(&self, hash: u32) -> (u32, u32, u32) {
+        let f1 = (hash >> (self.freq_bits + self.dt_bits)) & mask(self.freq_bits);
+        let f2 = (hash >> self.dt_bits) & mask(self.freq_bits);
+        let dt = hash & mask(self.dt_bits);
+        (f1 * self.freq_step, f2 * self.freq_step, dt)
+    }
+}
+
+// Lowest `bits` bits set
+fn mask(bits: u32) -> u32 {
+    if bits >= 32 {
+        u32::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+// Bits needed to represent `value`, at least one
+fn bits_for(value: u32) -> u32 {
+    (32 - value.leading_zeros()).max(1)
 }
@@ -0,0 +1,368 @@
+// Clip-to-song matching
+// Wang-style offset voting: every clip hash is looked up exactly in the song, each hit votes for
+// the time offset (song anchor - clip anchor) it implies, and the offset with the most votes is
+// the alignment. Random hash collisions scatter across offsets while a true match piles up in
+// one bin.
+//
+// How far the winning bin stands out is measured against the other offsets: its votes are
+// compared with the mean and spread of every other offset between the smallest and largest
+// one voted for (empty offsets count as zero). The result, in standard deviations, is the
+// same statistic whatever the clip length or hash density, and `Calibration` turns it into a
+// threshold for a chosen false-positive rate.
+//
+// `find_scaled_match` extends this to triplet hashes of a clip that may be faster, slower or
+// transposed. Every hit also measures a pitch factor, the ratio of the two anchor bins; the
+// hits that agree on it are voted by the offset they imply along
+// song frame = offset + tempo × clip frame, for every tempo in range, and the winning line is
+// refined by least squares.
+
+use super::hash::Fingerprint;
+use super::timebase::TimeBase;
+use super::triplet::TripletFingerprint;
+use std::collections::HashMap;
+
+/// Minimum number of agreeing hashes for `find_match` to report a match
+pub const MIN_VOTES: usize = 5;
+
+/// Floor of the background spread used by `OffsetHistogram::significance`, in votes, so a
+/// handful of votes over an almost empty background does not look significant
+const MIN_BACKGROUND_STD: f64 = 1.0;
+
+/// Largest tempo or pitch factor, or its inverse, that `find_scaled_match` considers;
+/// ±16% covers the widest common turntable pitch range
+pub const MAX_SCALE: f64 = 1.16;
+/// Width of the pitch histogram bins, in octaves
+const PITCH_STEP: f64 = 1.0 / 32.0;
+/// Tempo resolution of the coarse and fine alignment searches, in octaves
+const COARSE_TEMPO_STEP: f64 = 1.0 / 64.0;
+const FINE_TEMPO_STEP: f64 = 1.0 / 512.0;
+/// Distance in frames from the alignment line within which hits refine it
+const FIT_BAND_FRAMES: f64 = 3.0;
+/// Least-squares refinements of the alignment line
+const FIT_ROUNDS: usize = 3;
+
+/// The winning alignment of a clip against a song
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OffsetMatch {
+    /// Offset of the clip into the song, in frames
+    pub offset_frames: i64,
+    /// Offset of the clip into the song, in seconds
+    pub offset_secs: f64,
+    /// Number of clip hashes that agree on this offset
+    pub votes: usize,
+    /// Standard deviations by which `votes` exceeds the other offsets
+    pub significance: f64,
+}
+
+/// The votes for one offset and the clip frames they came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OffsetBin {
+    pub votes: usize,
+    /// Earliest and latest clip anchor frames that voted for this offset
+    pub first_clip_frame: usize,
+    pub last_clip_frame: usize,
+}
+
+/// Histogram of (song_time - clip_time) deltas
+#[derive(Debug, Default, Clone)]
+pub struct OffsetHistogram {
+    bins: HashMap<i64, OffsetBin>,
+}
+
+impl OffsetHistogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds one vote for the offset between a song anchor and a clip anchor
+    pub fn vote(&mut self, song_frame: usize, clip_frame: usize) {
+        let offset = song_frame as i64 - clip_frame as i64;
+        let bin = self.bins.entry(offset).or_insert(OffsetBin {
+            votes: 0,
+            first_clip_frame: clip_frame,
+            last_clip_frame: clip_frame,
+        });
+        bin.votes += 1;
+        bin.first_clip_frame = bin.first_clip_frame.min(clip_frame);
+        bin.last_clip_frame = bin.last_clip_frame.max(clip_frame);
+    }
+
+    /// Returns the offset bin with the most votes (the earliest one on ties)
+    pub fn best(&self) -> Option<(i64, usize)> {
+        self.bins
+            .iter()
+            .map(|(&offset, bin)| (offset, bin.votes))
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+    }
+
+    /// The votes for a single offset
+    pub fn bin(&self, offset: i64) -> Option<&OffsetBin> {
+        self.bins.get(&offset)
+    }
+
+    pub fn total_votes(&self) -> usize {
+        self.bins.values().map(|bin| bin.votes).sum()
+    }
+
+    /// Standard deviations by which the votes for `offset` exceed those of the other offsets.
+    /// The offset's immediate neighbours are left out of the background, since a true match
+    /// often spills into them. The spread is never taken below that of Poisson-distributed
+    /// counts with the same mean.
+    pub fn significance(&self, offset: i64) -> f64 {
+        let votes = self.bin(offset).map_or(0, |bin| bin.votes) as f64;
+        let (Some(&low), Some(&high)) = (self.bins.keys().min(), self.bins.keys().max()) else {
+            return 0.0;
+        };
+
+        let excluded = (offset - 1..=offset + 1)
+            .filter(|o| (low..=high).contains(o))
+            .count() as i64;
+        let background = (high - low + 1 - excluded) as f64;
+        let (mut sum, mut sum_sq) = (0.0, 0.0);
+        for (&o, bin) in &self.bins {
+            if o.abs_diff(offset) > 1 {
+                let v = bin.votes as f64;
+                sum += v;
+                sum_sq += v * v;
+            }
+        }
+
+        let (mean, variance) = if background > 0.0 {
+            let mean = sum / background;
+            (mean, (sum_sq / background - mean * mean).max(0.0))
+        } else {
+            (0.0, 0.0)
+        };
+        let std = variance.sqrt().max(mean.sqrt()).max(MIN_BACKGROUND_STD);
+        (votes - mean) / std
+    }
+}
+
+/// Finds where `clip_fingerprint` best aligns with `song_fingerprint` by offset voting.
+/// Returns `None` if fewer than `MIN_VOTES` hashes agree on any offset.
+pub fn find_match(
+    song_fingerprint: &[Fingerprint],
+    clip_fingerprint: &[Fingerprint],
+    time_base: &TimeBase,
+) -> Option<OffsetMatch> {
+    let histogram = offset_histogram(song_fingerprint, clip_fingerprint);
+    let (offset_frames, votes) = histogram.best()?;
+    if votes < MIN_VOTES {
+        return None;
+    }
+
+    Some(OffsetMatch {
+        offset_frames,
+        offset_secs: time_base.to_secs(offset_frames as f64),
+        votes,
+        significance: histogram.significance(offset_frames),
+    })
+}
+
+/// Votes every clip hash found in the song for the offset it implies
+pub fn offset_histogram(
+    song_fingerprint: &[Fingerprint],
+    clip_fingerprint: &[Fingerprint],
+) -> OffsetHistogram {
+    // Exact hash lookup table for the song
+    let mut song_hashes: HashMap<u32, Vec<usize>> = HashMap::new();
+    for fp in song_fingerprint {
+        song_hashes
+            .entry(fp.hash)
+            .or_default()
+            .push(fp.anchor_frame);
+    }
+
+    let mut histogram = OffsetHistogram::new();
+    for fp in clip_fingerprint {
+        if let Some(song_frames) = song_hashes.get(&fp.hash) {
+            for &song_frame in song_frames {
+                histogram.vote(song_frame, fp.anchor_frame);
+            }
+        }
+    }
+    histogram
+}
+
+/// The winning alignment of a clip that may be played at another speed or transposed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScaledMatch {
+    /// Song frame at which the clip starts
+    pub offset_frames: i64,
+    /// Song time at which the clip starts, in seconds
+    pub offset_secs: f64,
+    /// Clip speed relative to the song: song frames per clip frame
+    pub tempo_scale: f64,
+    /// Clip frequencies relative to the song
+    pub pitch_scale: f64,
+    /// Number of clip hashes that agree on this alignment, within one frame
+    pub votes: usize,
+}
+
+// A hash found in both recordings
+struct ScaledHit {
+    song_frame: f64,
+    clip_frame: f64,
+    // log2 of the pitch factor
+    pitch: f64,
+}
+
+impl ScaledHit {
+    // Song frame at which the clip would start if this hit lies on the alignment
+    fn offset(&self, tempo: f64) -> f64 {
+        self.song_frame - tempo * self.clip_frame
+    }
+}
+
+/// Finds where a tempo- and pitch-shifted clip best aligns with a song, from triplet hashes.
+/// Returns `None` if fewer than `MIN_VOTES` hashes agree on any alignment.
+pub fn find_scaled_match(
+    song_fingerprint: &[TripletFingerprint],
+    clip_fingerprint: &[TripletFingerprint],
+    time_base: &TimeBase,
+) -> Option<ScaledMatch> {
+    let mut song_hashes: HashMap<u32, Vec<&TripletFingerprint>> = HashMap::new();
+    for fp in song_fingerprint {
+        song_hashes.entry(fp.hash).or_default().push(fp);
+    }
+
+    let max_log = MAX_SCALE.log2();
+    let mut hits = Vec::new();
+    for clip in clip_fingerprint {
+        for song in song_hashes.get(&clip.hash).into_iter().flatten() {
+            let pitch = (clip.anchor_bin as f64 / song.anchor_bin as f64).log2();
+            if pitch.abs() <= max_log {
+                hits.push(ScaledHit {
+                    song_frame: song.anchor_frame as f64,
+                    clip_frame: clip.anchor_frame as f64,
+                    pitch,
+                });
+            }
+        }
+    }
+
+    // Pitch histogram; a bin's score includes its neighbours so a true match that straddles
+    // a bin edge is not split
+    let pitch_bin = |hit: &ScaledHit| (hit.pitch / PITCH_STEP).round() as i64;
+    let mut bins: HashMap<i64, usize> = HashMap::new();
+    for hit in &hits {
+        *bins.entry(pitch_bin(hit)).or_insert(0) += 1;
+    }
+    let score = |bin: i64| {
+        (bin - 1..=bin + 1)
+            .filter_map(|b| bins.get(&b))
+            .sum::<usize>()
+    };
+    let best_bin = bins
+        .keys()
+        .copied()
+        .max_by(|&a, &b| score(a).cmp(&score(b)).then(b.cmp(&a)))?;
+    let candidates: Vec<&ScaledHit> = hits
+        .iter()
+        .filter(|hit| pitch_bin(hit).abs_diff(best_bin) <= 1)
+        .collect();
+
+    // Offset voting for every tempo in range, coarsely and then around the best. The spans
+    // inside the hashes are only a few frames, too short to measure a few percent of tempo
+    // change, so the tempo comes from how hits line up over the whole clip instead.
+    let coarse_steps = (max_log / COARSE_TEMPO_STEP).ceil() as i64;
+    let (_, coarse, _) = best_alignment(
+        &candidates,
+        (-coarse_steps..=coarse_steps).map(|step| step as f64 * COARSE_TEMPO_STEP),
+    )?;
+    let fine_steps = (COARSE_TEMPO_STEP / FINE_TEMPO_STEP).round() as i64;
+    let (votes, log_tempo, offset) = best_alignment(
+        &candidates,
+        (-fine_steps..=fine_steps).map(|step| coarse + step as f64 * FINE_TEMPO_STEP),
+    )?;
+    if votes < MIN_VOTES {
+        return None;
+    }
+
+    // Many tempos near the winner collect the same votes, so the line is refined by least
+    // squares over the hits close to it
+    let mut tempo = log_tempo.exp2();
+    let mut intercept = offset as f64;
+    let mut inliers = Vec::new();
+    for _ in 0..FIT_ROUNDS {
+        inliers = candidates
+            .iter()
+            .copied()
+            .filter(|hit| (hit.offset(tempo) - intercept).abs() <= FIT_BAND_FRAMES)
+            .collect();
+        match fit_line(&inliers) {
+            Some((slope, fitted)) => (tempo, intercept) = (slope, fitted),
+            None => break,
+        }
+    }
+    let pitch_scale = median(inliers.iter().map(|hit| hit.pitch).collect()).exp2();
+
+    Some(ScaledMatch {
+        offset_frames: intercept.round() as i64,
+        offset_secs: time_base.to_secs(intercept.round()),
+        tempo_scale: tempo,
+        pitch_scale,
+        votes,
+    })
+}
+
+// The log2 tempo and offset that most hits agree on within one frame, with the votes for it.
+// Ties go to the tempo closest to 1, then to the earliest offset.
+fn best_alignment(
+    hits: &[&ScaledHit],
+    log_tempos: impl Iterator<Item = f64>,
+) -> Option<(usize, f64, i64)> {
+    let mut best: Option<(usize, f64, i64)> = None;
+    let mut offsets = Vec::with_capacity(hits.len());
+    for log_tempo in log_tempos {
+        let tempo = log_tempo.exp2();
+        offsets.clear();
+        offsets.extend(hits.iter().map(|hit| hit.offset(tempo).round() as i64));
+        offsets.sort_unstable();
+
+        // Two pointers bound the offsets within one frame of each distinct offset
+        let (mut low, mut high) = (0, 0);
+        for (i, &offset) in offsets.iter().enumerate() {
+            if i > 0 && offsets[i - 1] == offset {
+                continue;
+            }
+            while offsets[low] < offset - 1 {
+                low += 1;
+            }
+            while high < offsets.len() && offsets[high] <= offset + 1 {
+                high += 1;
+            }
+            let votes = high - low;
+            let better = best.is_none_or(|(best_votes, best_tempo, best_offset)| {
+                (votes, -log_tempo.abs(), -offset) > (best_votes, -best_tempo.abs(), -best_offset)
+            });
+            if better {
+                best = Some((votes, log_tempo, offset));
+            }
+        }
+    }
+    best
+}
+
+// Slope and intercept of song frame against clip frame, if the clip frames are not all equal
+fn fit_line(hits: &[&ScaledHit]) -> Option<(f64, f64)> {
+    let n = hits.len() as f64;
+    let mean_clip = hits.iter().map(|hit| hit.clip_frame).sum::<f64>() / n;
+    let mean_song = hits.iter().map(|hit| hit.song_frame).sum::<f64>() / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for hit in hits {
+        let dx = hit.clip_frame - mean_clip;
+        covariance += dx * (hit.song_frame - mean_song);
+        variance += dx * dx;
+    }
+    if variance == 0.0 {
+        return None;
+    }
+    let slope = covariance / variance;
+    Some((slope, mean_song - slope * mean_clip))
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    values.get(values.len() / 2).copied().unwrap_or(0.0)
+}
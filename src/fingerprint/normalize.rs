@@ -0,0 +1,70 @@
+// Spectrum normalization
+// Applies the config's `Normalization` to spectrogram frames in time order. Strategies that
+// look at more than the current frame keep a short history, so the same normalizer serves the
+// batch pipeline and the streaming one and both see identical values.
+
+use crate::fingerprint::config::{FingerprintConfig, Normalization};
+use std::collections::VecDeque;
+
+/// Length of the history used by `Normalization::Rms`
+const RMS_WINDOW_SECS: f64 = 1.0;
+/// Levels below this are treated as silence and left unscaled
+const SILENCE_FLOOR: f64 = 1e-12;
+
+#[derive(Debug, Clone)]
+pub struct SpectrumNormalizer {
+    mode: Normalization,
+    window_frames: usize,
+    // Mean squared magnitude of the most recent frames, for `Rms`
+    energies: VecDeque<f64>,
+}
+
+impl SpectrumNormalizer {
+    pub fn new(config: &FingerprintConfig) -> Self {
+        let frames_per_sec = config.time_base().frames_per_second();
+        SpectrumNormalizer {
+            mode: config.normalization,
+            window_frames: ((RMS_WINDOW_SECS * frames_per_sec).ceil() as usize).max(1),
+            energies: VecDeque::new(),
+        }
+    }
+
+    /// Normalizes the next frame in place
+    pub fn apply(&mut self, spectrum: &mut [f64]) {
+        match self.mode {
+            // Peak scaling happens on the samples, before the FFT
+            Normalization::None | Normalization::Peak => {}
+            Normalization::PerFrame => {
+                let rms = mean_square(spectrum).sqrt();
+                scale(spectrum, rms);
+            }
+            Normalization::Rms => {
+                self.energies.push_back(mean_square(spectrum));
+                if self.energies.len() > self.window_frames {
+                    self.energies.pop_front();
+                }
+                let rms = (self.energies.iter().sum::<f64>() / self.energies.len() as f64).sqrt();
+                scale(spectrum, rms);
+            }
+            Normalization::LogMagnitude => {
+                let mean = spectrum.iter().sum::<f64>() / spectrum.len().max(1) as f64;
+                let level = if mean > SILENCE_FLOOR { mean } else { 1.0 };
+                for x in spectrum.iter_mut() {
+                    *x = (*x / level).ln_1p();
+                }
+            }
+        }
+    }
+}
+
+fn mean_square(spectrum: &[f64]) -> f64 {
+    spectrum.iter().map(|x| x * x).sum::<f64>() / spectrum.len().max(1) as f64
+}
+
+fn scale(spectrum: &mut [f64], level: f64) {
+    if level > SILENCE_FLOOR {
+        for x in spectrum.iter_mut() {
+            *x /= level;
+        }
+    }
+}
@@ -4,6 +4,19 @@
 // Dividing into Bands: Each frame's frequency bins are divided into a fixed number of bands (in our case, 6). This ensures that we capture distinct frequency components across the spectrum.
 // Selecting the Maximum: Within each band, the algorithm finds the frequency bin with the highest magnitude (the peak) and records its frame index, frequency bin, and amplitude.
 
+// Constellation picking: instead of one peak per band, a bin is kept only when it is the largest
+// value in a rectangle of neighbouring frames and bins. Those local maxima are then thinned to a
+// fixed number per second, keeping the strongest, so quiet passages and noise do not fill the
+// fingerprint with arbitrary peaks.
+
+use crate::fingerprint::bands::{band_ranges, linear_bands};
+use crate::fingerprint::config::{FingerprintConfig, PeakPicker};
+use std::collections::VecDeque;
+use std::ops::Range;
+
+/// Values at or below this are never peaks
+const SILENCE_FLOOR: f64 = 1e-12;
+
 #[derive(Debug, Clone, Copy)]
 pub struct Peak {
     pub frame_index: usize,
@@ -18,46 +31,242 @@ pub fn detect_peaks(
     threshold_multiplier: f64,
 ) -> Vec<Peak> {
     let mut peaks = Vec::new();
+    let bands = linear_bands(spectrogram.first().map_or(0, Vec::len), num_of_bands);
 
     for (i, frame) in spectrogram.iter().enumerate() {
-        let num_bins = frame.len();
-        let band_size = num_bins / num_of_bands;
-
-        for band in 0..num_of_bands {
-            let start = band * band_size;
-            let mut end = start + band_size;
-            if band == num_of_bands - 1 {
-                end = num_bins;
+        detect_band_peaks(i, frame, &bands, threshold_multiplier, &mut peaks);
+    }
+
+    peaks
+}
+
+/// Appends the peaks of a single spectrogram frame, in band order, for the given band bin
+/// ranges (see `bands::band_ranges`)
+pub fn detect_band_peaks(
+    frame_index: usize,
+    frame: &[f64],
+    bands: &[Range<usize>],
+    threshold_multiplier: f64,
+    peaks: &mut Vec<Peak>,
+) {
+    for band in bands {
+        let (start, end) = (band.start, band.end);
+        if start >= end {
+            continue;
+        }
+
+        // Calculate average magnitude in the current band
+        let sum_magnitude: f64 = frame[start..end].iter().sum();
+        let average_magnitude = sum_magnitude / (end - start) as f64;
+        let local_threshold = average_magnitude * threshold_multiplier; // Tunable parameter
+
+        let mut max_val = -1.0;
+        let mut max_bin = None;
+
+        for (j, &value) in frame.iter().enumerate().take(end).skip(start) {
+            if value > max_val && value > local_threshold {
+                // Apply local threshold
+                max_val = value;
+                max_bin = Some(j);
             }
+        }
 
-            // Calculate average magnitude in the current band
-            let mut sum_magnitude = 0.0;
-            for j in start..end {
-                sum_magnitude += frame[j];
+        if let Some(bin) = max_bin {
+            peaks.push(Peak {
+                frame_index,
+                freq_bin: bin,
+                magnitude: max_val,
+            });
+        }
+    }
+}
+
+/// Picks peaks from spectrogram frames pushed in time order, with the strategy from the config.
+/// Batch and streaming pipelines both go through this, so they select the same peaks.
+#[derive(Debug, Clone)]
+pub struct PeakDetector {
+    picker: Picker,
+}
+
+#[derive(Debug, Clone)]
+enum Picker {
+    BandMax {
+        bands: Vec<Range<usize>>,
+        threshold_multiplier: f64,
+        frames: usize,
+    },
+    Constellation(ConstellationPicker),
+}
+
+impl PeakDetector {
+    pub fn new(config: &FingerprintConfig) -> Self {
+        let picker = match config.peak_picker {
+            PeakPicker::BandMax => Picker::BandMax {
+                bands: band_ranges(config),
+                threshold_multiplier: config.threshold_multiplier,
+                frames: 0,
+            },
+            PeakPicker::Constellation {
+                neighborhood_frames,
+                neighborhood_bins,
+                peaks_per_second,
+            } => {
+                let frames_per_sec = config.time_base().frames_per_second();
+                let block_frames = (frames_per_sec.ceil() as usize).max(1);
+                let per_block = (peaks_per_second * block_frames as f64 / frames_per_sec).round();
+
+                Picker::Constellation(ConstellationPicker {
+                    neighborhood_frames,
+                    neighborhood_bins,
+                    threshold_multiplier: config.threshold_multiplier,
+                    block_frames,
+                    peaks_per_block: (per_block as usize).max(1),
+                    frames: VecDeque::new(),
+                    first_buffered: 0,
+                    received: 0,
+                    next_frame: 0,
+                    block_start: 0,
+                    candidates: Vec::new(),
+                })
             }
-            let average_magnitude = sum_magnitude / (end - start) as f64;
-            let local_threshold = average_magnitude * threshold_multiplier; // Tunable parameter
-
-            let mut max_val = -1.0;
-            let mut max_bin = None;
-
-            for j in start..end {
-                if frame[j] > max_val && frame[j] > local_threshold {
-                    // Apply local threshold
-                    max_val = frame[j];
-                    max_bin = Some(j);
-                }
+        };
+        PeakDetector { picker }
+    }
+
+    /// Takes the next frame and appends every peak that can no longer change
+    pub fn push_frame(&mut self, spectrum: &[f64], peaks: &mut Vec<Peak>) {
+        match &mut self.picker {
+            Picker::BandMax {
+                bands,
+                threshold_multiplier,
+                frames,
+            } => {
+                detect_band_peaks(*frames, spectrum, bands, *threshold_multiplier, peaks);
+                *frames += 1;
             }
+            Picker::Constellation(picker) => picker.push_frame(spectrum, peaks),
+        }
+    }
 
-            if let Some(bin) = max_bin {
-                peaks.push(Peak {
-                    frame_index: i,
+    /// Appends the remaining peaks once no more frames will arrive
+    pub fn finish(&mut self, peaks: &mut Vec<Peak>) {
+        if let Picker::Constellation(picker) = &mut self.picker {
+            picker.finish(peaks);
+        }
+    }
+
+    /// Every peak in frames before this one has been returned
+    pub fn settled_frames(&self) -> usize {
+        match &self.picker {
+            Picker::BandMax { frames, .. } => *frames,
+            Picker::Constellation(picker) => picker.block_start,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ConstellationPicker {
+    neighborhood_frames: usize,
+    neighborhood_bins: usize,
+    threshold_multiplier: f64,
+    block_frames: usize,
+    peaks_per_block: usize,
+    // Recent frames with the maximum over each bin's frequency neighbourhood
+    frames: VecDeque<(Vec<f64>, Vec<f64>)>,
+    first_buffered: usize,
+    received: usize,
+    // Next frame to test for local maxima
+    next_frame: usize,
+    // First frame of the block whose candidates are being collected
+    block_start: usize,
+    candidates: Vec<Peak>,
+}
+
+impl ConstellationPicker {
+    fn push_frame(&mut self, spectrum: &[f64], peaks: &mut Vec<Peak>) {
+        self.frames
+            .push_back((spectrum.to_vec(), self.frequency_max(spectrum)));
+        self.received += 1;
+
+        // A frame can be tested once all frames of its neighbourhood have arrived
+        while self.next_frame + self.neighborhood_frames < self.received {
+            self.test_frame(peaks);
+        }
+
+        while self.first_buffered + self.neighborhood_frames < self.next_frame {
+            self.frames.pop_front();
+            self.first_buffered += 1;
+        }
+    }
+
+    fn finish(&mut self, peaks: &mut Vec<Peak>) {
+        while self.next_frame < self.received {
+            self.test_frame(peaks);
+        }
+        self.flush_block(peaks);
+        self.block_start = self.received;
+    }
+
+    // Collects the local maxima of `next_frame` as candidates of its block
+    fn test_frame(&mut self, peaks: &mut Vec<Peak>) {
+        let t = self.next_frame;
+        while t >= self.block_start + self.block_frames {
+            self.flush_block(peaks);
+            self.block_start += self.block_frames;
+        }
+
+        let first = t.saturating_sub(self.neighborhood_frames);
+        let last = (t + self.neighborhood_frames).min(self.received - 1);
+        let (spectrum, _) = &self.frames[t - self.first_buffered];
+        let mean = spectrum.iter().sum::<f64>() / spectrum.len() as f64;
+        let threshold = (mean * self.threshold_multiplier).max(SILENCE_FLOOR);
+
+        for (bin, &value) in spectrum.iter().enumerate() {
+            if value <= threshold {
+                continue;
+            }
+            let is_maximum = (first..=last).all(|frame| {
+                let (_, frequency_max) = &self.frames[frame - self.first_buffered];
+                frequency_max[bin] <= value
+            });
+            if is_maximum {
+                self.candidates.push(Peak {
+                    frame_index: t,
                     freq_bin: bin,
-                    magnitude: max_val,
+                    magnitude: value,
                 });
             }
         }
+
+        self.next_frame += 1;
     }
 
-    peaks
+    // Keeps the strongest candidates of the block, in time then frequency order
+    fn flush_block(&mut self, peaks: &mut Vec<Peak>) {
+        self.candidates.sort_by(|a, b| {
+            b.magnitude
+                .total_cmp(&a.magnitude)
+                .then(a.frame_index.cmp(&b.frame_index))
+                .then(a.freq_bin.cmp(&b.freq_bin))
+        });
+        self.candidates.truncate(self.peaks_per_block);
+        self.candidates
+            .sort_by_key(|peak| (peak.frame_index, peak.freq_bin));
+        peaks.append(&mut self.candidates);
+    }
+
+    // Maximum over each bin's ±neighborhood_bins range within one frame
+    fn frequency_max(&self, spectrum: &[f64]) -> Vec<f64> {
+        let n = spectrum.len();
+        (0..n)
+            .map(|bin| {
+                let start = bin.saturating_sub(self.neighborhood_bins);
+                let end = (bin + self.neighborhood_bins + 1).min(n);
+                spectrum[start..end]
+                    .iter()
+                    .copied()
+                    .fold(f64::NEG_INFINITY, f64::max)
+            })
+            .collect()
+    }
 }
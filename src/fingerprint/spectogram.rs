@@ -1,21 +1,25 @@
 // Parallel Processing: spectogram.rs's computeSpectrogram function applies the FFT on each frame concurrently using goroutines, speeding up the process.
 
-use crate::dsp::fft::compute_fft;
+use crate::dsp::fft::{SpectrumAnalyzer, SpectrumScale};
 use rayon::prelude::*;
 
 pub fn compute_spectrogram(frames: Vec<Vec<f32>>, window: Vec<f32>) -> Vec<Vec<f64>> {
-    // Process frames in parallel using rayon
+    let analyzer = SpectrumAnalyzer::new(window.len(), SpectrumScale::Magnitude)
+        .with_window(window.iter().map(|&w| w as f64).collect());
+
+    // Process frames in parallel using rayon, one scratch buffer per worker
     frames
         .par_iter()
-        .map(|frame| {
-            // Apply window function and compute FFT
-            let windowed_frame: Vec<f32> = frame
-                .iter()
-                .zip(window.iter())
-                .map(|(&sample, &window_val)| sample * window_val)
-                .collect();
-
-            compute_fft(windowed_frame)
-        })
+        .map_init(
+            || (analyzer.scratch(), vec![0.0; analyzer.frame_size()]),
+            |(scratch, frame64), frame| {
+                for (x, &sample) in frame64.iter_mut().zip(frame) {
+                    *x = sample as f64;
+                }
+                let mut spectrum = vec![0.0; analyzer.num_bins()];
+                analyzer.process_into(frame64, scratch, &mut spectrum);
+                spectrum
+            },
+        )
         .collect()
 }
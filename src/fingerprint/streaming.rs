@@ -0,0 +1,192 @@
+// Streaming fingerprinting
+// Runs the same pipeline as `finger_print` over a signal that arrives in chunks. Each stage
+// keeps only the state it needs to continue: the resampler its filter history, the framer the
+// samples of the next frame, the peak picker its unsettled frames, and the hasher the peaks
+// whose target zone is still open.
+// A fingerprint is emitted as soon as every frame its anchor can pair with has been analyzed.
+//
+// Samples are taken at i16 full scale and normalized per frame by `SpectrumNormalizer`, exactly
+// as in `finger_print`. `Normalization::Peak` needs the loudest sample of the whole signal,
+// which a stream cannot know in advance, so it is rejected.
+
+use crate::dsp::fft::{SpectrumAnalyzer, SpectrumScale, SpectrumScratch};
+use crate::dsp::resample::{Resampler, ResamplerStream};
+use crate::error::{NumeroError, Result};
+use crate::fingerprint::config::{FingerprintConfig, Normalization};
+use crate::fingerprint::fingerprint::{check_sample_rate, FingerprintSet};
+use crate::fingerprint::hash::{hash_anchor, Fingerprint};
+use crate::fingerprint::normalize::SpectrumNormalizer;
+use crate::fingerprint::peaks::{Peak, PeakDetector};
+use crate::fingerprint::utils::hamming_window;
+use std::collections::VecDeque;
+
+/// Fingerprints a signal supplied as blocks of mono samples, e.g. from `wav::open_audio_blocks`,
+/// without holding more than one block in memory
+pub fn finger_print_blocks<I>(
+    blocks: I,
+    sample_rate: u32,
+    config: &FingerprintConfig,
+) -> Result<FingerprintSet>
+where
+    I: IntoIterator<Item = Vec<i16>>,
+{
+    let mut fingerprinter = StreamingFingerprinter::new(sample_rate, config)?;
+    let mut fingerprints = Vec::new();
+    for block in blocks {
+        fingerprints.extend(fingerprinter.push(&block));
+    }
+
+    // Same checks as `analyze`, which can only run once the length is known
+    let samples = fingerprinter.samples_received();
+    if samples == 0 {
+        return Err(NumeroError::EmptyInput);
+    }
+    let resampler = fingerprinter.resampler.resampler();
+    if resampler.output_len(samples) < config.frame_size {
+        let (up, down) = resampler.ratio();
+        return Err(NumeroError::TooShort {
+            samples,
+            required: (config.frame_size * down).div_ceil(up),
+        });
+    }
+    fingerprints.extend(fingerprinter.finish());
+
+    Ok(FingerprintSet {
+        config: config.clone(),
+        fingerprints,
+    })
+}
+
+/// Incremental fingerprinter with memory bounded by the config, not the signal length
+#[derive(Debug, Clone)]
+pub struct StreamingFingerprinter {
+    config: FingerprintConfig,
+    sample_rate: u32,
+    samples_received: usize,
+    resampler: ResamplerStream,
+    analyzer: SpectrumAnalyzer,
+    scratch: SpectrumScratch,
+    // Resampled samples from the start of the next frame onwards
+    pending: Vec<f64>,
+    spectrum: Vec<f64>,
+    normalizer: SpectrumNormalizer,
+    detector: PeakDetector,
+    next_frame: usize,
+    // Peaks that are still anchors or can still be targets, in detection order
+    peaks: VecDeque<Peak>,
+    frame_peaks: Vec<Peak>,
+}
+
+impl StreamingFingerprinter {
+    pub fn new(sample_rate: u32, config: &FingerprintConfig) -> Result<Self> {
+        config.validate()?;
+        check_sample_rate(sample_rate, config)?;
+        if config.normalization == Normalization::Peak {
+            return Err(NumeroError::InvalidConfig(
+                "peak normalization needs the whole signal and cannot be streamed".to_string(),
+            ));
+        }
+
+        let resampler = Resampler::new(sample_rate, config.target_sample_rate, config.filter_taps);
+        let analyzer = SpectrumAnalyzer::new(config.frame_size, SpectrumScale::Magnitude)
+            .with_window(hamming_window(config.frame_size));
+
+        Ok(StreamingFingerprinter {
+            config: config.clone(),
+            sample_rate,
+            samples_received: 0,
+            resampler: resampler.into_stream(),
+            scratch: analyzer.scratch(),
+            spectrum: vec![0.0; analyzer.num_bins()],
+            analyzer,
+            normalizer: SpectrumNormalizer::new(config),
+            detector: PeakDetector::new(config),
+            pending: Vec::new(),
+            next_frame: 0,
+            peaks: VecDeque::new(),
+            frame_peaks: Vec::new(),
+        })
+    }
+
+    pub fn config(&self) -> &FingerprintConfig {
+        &self.config
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Input samples consumed so far
+    pub fn samples_received(&self) -> usize {
+        self.samples_received
+    }
+
+    /// Frames analyzed so far; anchor frames are counted from the start of the stream
+    pub fn frames_processed(&self) -> usize {
+        self.next_frame
+    }
+
+    /// Consumes a chunk of mono PCM and returns the fingerprints completed by it
+    pub fn push(&mut self, samples: &[i16]) -> Vec<Fingerprint> {
+        self.samples_received += samples.len();
+
+        let scaled: Vec<f64> = samples.iter().map(|&s| s as f64 / 32768.0).collect();
+        self.resampler.push(&scaled, &mut self.pending);
+
+        self.analyze_pending_frames();
+        self.emit_closed_anchors(false)
+    }
+
+    /// Ends the stream and returns the remaining fingerprints
+    pub fn finish(mut self) -> Vec<Fingerprint> {
+        self.resampler.finish(&mut self.pending);
+
+        self.analyze_pending_frames();
+        self.frame_peaks.clear();
+        self.detector.finish(&mut self.frame_peaks);
+        self.peaks.extend(self.frame_peaks.iter().copied());
+        self.emit_closed_anchors(true)
+    }
+
+    fn analyze_pending_frames(&mut self) {
+        let frame_size = self.config.frame_size;
+        let hop_size = self.config.hop_size;
+
+        let mut start = 0;
+        while start + frame_size <= self.pending.len() {
+            self.analyzer.process_into(
+                &self.pending[start..start + frame_size],
+                &mut self.scratch,
+                &mut self.spectrum,
+            );
+            self.normalizer.apply(&mut self.spectrum);
+
+            self.frame_peaks.clear();
+            self.detector
+                .push_frame(&self.spectrum, &mut self.frame_peaks);
+            self.peaks.extend(self.frame_peaks.iter().copied());
+
+            self.next_frame += 1;
+            start += hop_size;
+        }
+        self.pending.drain(..start);
+    }
+
+    // Pairs and drops every anchor whose target zone lies entirely within the frames whose
+    // peaks are final
+    fn emit_closed_anchors(&mut self, end_of_stream: bool) -> Vec<Fingerprint> {
+        let target_zone = self.config.target_zone_frames;
+        let settled = self.detector.settled_frames();
+        let mut fingerprints = Vec::new();
+
+        while let Some(anchor) = self.peaks.front().copied() {
+            if !end_of_stream && anchor.frame_index + target_zone >= settled {
+                break;
+            }
+            self.peaks.pop_front();
+            hash_anchor(&anchor, &self.peaks, &self.config, &mut fingerprints);
+        }
+
+        fingerprints
+    }
+}
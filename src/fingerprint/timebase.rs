@@ -0,0 +1,49 @@
+// Frame timing
+// Fingerprints locate their anchors by frame index. A frame starts every `hop_size` samples of
+// the resampled signal, and the resampler converts by an exact rational ratio, so one frame
+// lasts exactly `hop_size / target_sample_rate` seconds. Every conversion between frames and
+// seconds goes through `TimeBase` so matchers report times for the config actually used.
+
+use crate::fingerprint::config::FingerprintConfig;
+
+/// Converts between frame indices and seconds for one fingerprint config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeBase {
+    /// Rate of the analyzed signal (Hz)
+    pub sample_rate: u32,
+    /// Samples between the starts of consecutive frames
+    pub hop_size: usize,
+}
+
+impl TimeBase {
+    pub fn new(sample_rate: u32, hop_size: usize) -> Self {
+        TimeBase {
+            sample_rate,
+            hop_size,
+        }
+    }
+
+    /// The time base of the frames produced with `config`
+    pub fn from_config(config: &FingerprintConfig) -> Self {
+        Self::new(config.target_sample_rate, config.hop_size)
+    }
+
+    /// Duration of one frame in seconds
+    pub fn seconds_per_frame(&self) -> f64 {
+        self.hop_size as f64 / self.sample_rate as f64
+    }
+
+    pub fn frames_per_second(&self) -> f64 {
+        self.sample_rate as f64 / self.hop_size as f64
+    }
+
+    /// Seconds from frame 0 to a (possibly negative or fractional) frame position
+    pub fn to_secs(&self, frames: f64) -> f64 {
+        frames * self.seconds_per_frame()
+    }
+
+    /// Frame position of a time in seconds
+    pub fn to_frames(&self, secs: f64) -> f64 {
+        secs * self.frames_per_second()
+    }
+}
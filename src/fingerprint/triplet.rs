@@ -0,0 +1,116 @@
+// Triplet hashes
+// Playing a recording faster scales every frame difference by the same factor, and transposing
+// it scales every frequency by the same factor. A hash built only from ratios of those
+// quantities is unchanged by both:
+// - log2(target frequency / anchor frequency) for two targets, in `STEPS_PER_OCTAVE` steps
+// - first target's frame difference / second target's frame difference, in
+//   `TIME_RATIO_STEPS` steps
+// Each triplet also keeps its anchor's bin, from which a matcher can recover the pitch factor
+// that relates two recordings; the tempo factor follows from where the anchors line up.
+
+use crate::error::Result;
+use crate::fingerprint::config::FingerprintConfig;
+use crate::fingerprint::fingerprint::analyze;
+use crate::fingerprint::hash::{select_targets, Fingerprint};
+use crate::fingerprint::peaks::Peak;
+
+/// Quantization steps per octave of the frequency ratios
+pub const STEPS_PER_OCTAVE: f64 = 12.0;
+/// Quantization steps of the frame difference ratio between 0 and 1
+pub const TIME_RATIO_STEPS: f64 = 16.0;
+/// Targets combined per anchor; larger target zones are sampled evenly down to this many
+pub const MAX_TRIPLET_TARGETS: usize = 8;
+
+const FREQ_RATIO_BITS: u32 = 8;
+const TIME_RATIO_BITS: u32 = 5;
+
+/// A triplet hash with the anchor measurements a scale-tolerant matcher needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TripletFingerprint {
+    pub hash: u32,
+    pub anchor_frame: usize,
+    /// Frequency bin of the anchor peak
+    pub anchor_bin: usize,
+}
+
+impl From<TripletFingerprint> for Fingerprint {
+    fn from(triplet: TripletFingerprint) -> Self {
+        Fingerprint {
+            hash: triplet.hash,
+            anchor_frame: triplet.anchor_frame,
+        }
+    }
+}
+
+/// Triplet fingerprints of a whole signal, whatever the config's hash family
+pub fn finger_print_triplets(
+    samples: &[i16],
+    sample_rate: u32,
+    config: &FingerprintConfig,
+) -> Result<Vec<TripletFingerprint>> {
+    let analysis = analyze(samples, sample_rate, config)?;
+    Ok(triplet_fingerprints(&analysis.peaks, config))
+}
+
+/// Combines every anchor with pairs of the targets chosen by the config's `Pairing`
+pub fn triplet_fingerprints(peaks: &[Peak], config: &FingerprintConfig) -> Vec<TripletFingerprint> {
+    peaks
+        .iter()
+        .enumerate()
+        .flat_map(|(i, anchor)| {
+            let targets = select_targets(anchor, &peaks[i + 1..], config);
+            triplet_anchor(anchor, &targets)
+        })
+        .collect()
+}
+
+/// Packs an anchor and two targets, given with their frame differences, into a triplet hash.
+/// The first target must not come after the second.
+pub fn triplet_hash(anchor: &Peak, first: (&Peak, usize), second: (&Peak, usize)) -> u32 {
+    let ratio = |target: &Peak| {
+        let steps = (target.freq_bin as f64 / anchor.freq_bin as f64).log2() * STEPS_PER_OCTAVE;
+        let half = 1i64 << (FREQ_RATIO_BITS - 1);
+        (steps.round() as i64).clamp(-half, half - 1) + half
+    };
+    let time = (first.1 as f64 / second.1 as f64 * TIME_RATIO_STEPS).round() as u32;
+
+    ((ratio(first.0) as u32) << (FREQ_RATIO_BITS + TIME_RATIO_BITS))
+        | ((ratio(second.0) as u32) << TIME_RATIO_BITS)
+        | time.min((1 << TIME_RATIO_BITS) - 1)
+}
+
+// Every pair of targets, earlier target first. The DC bin has no meaningful ratio and pairs
+// in a single frame have no time ratio, so both are skipped.
+pub(crate) fn triplet_anchor(anchor: &Peak, targets: &[(&Peak, usize)]) -> Vec<TripletFingerprint> {
+    let mut triplets = Vec::new();
+    if anchor.freq_bin == 0 {
+        return triplets;
+    }
+
+    let usable: Vec<(&Peak, usize)> = targets
+        .iter()
+        .copied()
+        .filter(|(target, _)| target.freq_bin > 0)
+        .collect();
+    let sampled: Vec<(&Peak, usize)> = if usable.len() > MAX_TRIPLET_TARGETS {
+        (0..MAX_TRIPLET_TARGETS)
+            .map(|i| usable[i * usable.len() / MAX_TRIPLET_TARGETS])
+            .collect()
+    } else {
+        usable
+    };
+
+    for (i, &first) in sampled.iter().enumerate() {
+        for &second in &sampled[i + 1..] {
+            if second.1 == 0 {
+                continue;
+            }
+            triplets.push(TripletFingerprint {
+                hash: triplet_hash(anchor, first, second),
+                anchor_frame: anchor.frame_index,
+                anchor_bin: anchor.freq_bin,
+            });
+        }
+    }
+    triplets
+}
@@ -5,7 +5,7 @@
 
 pub fn frame_signal(signal: &[f64], frame_size: usize, hop_size: usize) -> Vec<Vec<f64>> {
     let mut frames: Vec<Vec<f64>> = Vec::new();
-    let mut n = signal.len();
+    let n = signal.len();
 
     for start in (0..n).step_by(hop_size) {
         let end = start + frame_size;
@@ -26,9 +26,8 @@ pub fn hamming_window(n: usize) -> Vec<f64> {
     let mut window = vec![0.0; n];
     if n > 1 {
         // Avoid division by zero if n is 1
-        for i in 0..n {
-            window[i] =
-                0.54 - 0.46 * (2.0 * std::f64::consts::PI * i as f64 / (n as f64 - 1.0)).cos();
+        for (i, w) in window.iter_mut().enumerate() {
+            *w = 0.54 - 0.46 * (2.0 * std::f64::consts::PI * i as f64 / (n as f64 - 1.0)).cos();
         }
     } else if n == 1 {
         window[0] = 1.0;
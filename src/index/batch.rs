@@ -0,0 +1,580 @@
+// Batch indexing
+// Walks a directory tree and fingerprints every audio file on a rayon pool, then adds the
+// results to one index. Files are processed in groups of `files_in_flight`: the group is
+// decoded and fingerprinted in parallel, and its fingerprint sets are added in path order
+// before the next group starts. Track ids therefore do not depend on scheduling, and at most
+// one group of fingerprint sets is held besides the index itself. Each file is decoded block
+// by block, so its audio never has to fit in memory.
+//
+// Runs are incremental. Every file added is recorded in the index manifest under its absolute
+// path, with the directories resolved, so a library is recognized however its path is spelled.
+// A later run skips files that have not changed since, re-indexes those that have under a new
+// track id, and removes the tracks of files that are gone. Tracks added without the manifest
+// are adopted by name: a file whose recorded path matches such a track replaces it.
+//
+// A file that cannot be decoded or fingerprinted is recorded in the report and skipped, and
+// the track of an earlier version stays in the index until the file can be read again; only
+// an unreadable root directory or a failure to start the thread pool stops the run.
+
+use super::manifest::{file_checksum, Checksum, FileStamp, ManifestEntry};
+use super::{FingerprintIndex, TrackId};
+use crate::error::{NumeroError, Result};
+use crate::fingerprint::config::{FingerprintConfig, Normalization};
+use crate::fingerprint::fingerprint::{finger_print, FingerprintSet};
+use crate::fingerprint::streaming::finger_print_blocks;
+use crate::wav::decode_audio_blocks;
+use rayon::prelude::*;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// File extensions picked up by `BatchIndexer::index_dir`, compared case-insensitively
+pub const AUDIO_EXTENSIONS: [&str; 4] = ["wav", "mp3", "flac", "ogg"];
+
+/// Mono samples decoded at a time
+const DEFAULT_BLOCK_SIZE: usize = 1 << 16;
+
+/// Decodes and fingerprints a file block by block so long recordings stay out of memory.
+/// Peak normalization needs the whole signal at once.
+pub fn fingerprint_file(
+    path: &Path,
+    config: &FingerprintConfig,
+    block_size: usize,
+) -> Result<FingerprintSet> {
+    fingerprint_source(path, config, block_size).map(|(set, _, _)| set)
+}
+
+// `fingerprint_file` that also returns the duration of the decoded audio in seconds and the
+// checksum of the file, taken from the bytes as the decoder reads them
+fn fingerprint_source(
+    path: &Path,
+    config: &FingerprintConfig,
+    block_size: usize,
+) -> Result<(FingerprintSet, f64, u64)> {
+    let reader = ChecksumReader::open(path)?;
+    let progress = reader.progress.clone();
+    let mut blocks = decode_audio_blocks(reader, block_size)?;
+    let sample_rate = blocks.sample_rate();
+
+    let (set, samples) = if config.normalization == Normalization::Peak {
+        let (samples, _) = blocks.read_all()?;
+        (finger_print(&samples, sample_rate, config)?, samples.len())
+    } else {
+        let set = finger_print_blocks(&mut blocks, sample_rate, config)?;
+        // The same checks `read_audio_file` applies, so files are accepted alike everywhere
+        blocks.check_format()?;
+        (set, blocks.samples_read())
+    };
+    let checksum = finish_checksum(path, &progress)?;
+    Ok((set, samples as f64 / sample_rate as f64, checksum))
+}
+
+// How far into a file the bytes read so far have been checksummed
+#[derive(Debug)]
+struct ChecksumProgress {
+    checksum: Checksum,
+    hashed_to: u64,
+}
+
+// A file reader that checksums the bytes it hands out. Decoders mostly read straight through,
+// so the checksum is usually complete when decoding ends; bytes they skip or seek past are
+// read afterwards by `finish_checksum`.
+struct ChecksumReader {
+    file: BufReader<File>,
+    position: u64,
+    progress: Arc<Mutex<ChecksumProgress>>,
+}
+
+impl ChecksumReader {
+    fn open(path: &Path) -> Result<Self> {
+        Ok(ChecksumReader {
+            file: BufReader::new(File::open(path)?),
+            position: 0,
+            progress: Arc::new(Mutex::new(ChecksumProgress {
+                checksum: Checksum::new(),
+                hashed_to: 0,
+            })),
+        })
+    }
+}
+
+impl Read for ChecksumReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.file.read(buf)?;
+        let end = self.position + read as u64;
+        let mut progress = self.progress.lock().unwrap();
+        if (self.position..end).contains(&progress.hashed_to) {
+            let first = (progress.hashed_to - self.position) as usize;
+            progress.checksum.update(&buf[first..read]);
+            progress.hashed_to = end;
+        }
+        self.position = end;
+        Ok(read)
+    }
+}
+
+impl Seek for ChecksumReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = self.file.seek(pos)?;
+        Ok(self.position)
+    }
+}
+
+// Checksums whatever the decoder did not read in order
+fn finish_checksum(path: &Path, progress: &Mutex<ChecksumProgress>) -> Result<u64> {
+    let mut progress = progress.lock().unwrap();
+    let mut file = BufReader::new(File::open(path)?);
+    file.seek(SeekFrom::Start(progress.hashed_to))?;
+    progress.checksum.update_from(file)?;
+    Ok(progress.checksum.value())
+}
+
+/// Reported once for every file that needed processing, as soon as it is done. Files finish
+/// out of order, and the callback runs on the worker threads.
+#[derive(Debug)]
+pub struct BatchProgress<'a> {
+    /// Files finished so far, this one included
+    pub completed: usize,
+    /// Files that needed processing; unchanged files are not counted
+    pub total: usize,
+    pub path: &'a Path,
+    pub outcome: FileOutcome<'a>,
+}
+
+/// What processing a file came to
+#[derive(Debug)]
+pub enum FileOutcome<'a> {
+    /// Fingerprinted; the fingerprint count
+    Indexed(usize),
+    /// Modified on disk but with the same content as when it was indexed
+    Unchanged,
+    /// Skipped, and why
+    Failed(&'a NumeroError),
+}
+
+/// A file that was added to the index
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedFile {
+    pub path: PathBuf,
+    pub track_id: TrackId,
+    pub fingerprints: usize,
+    pub duration_secs: f64,
+    /// Track of an earlier version of the file, now removed
+    pub replaced: Option<TrackId>,
+}
+
+/// A track removed because its source file is gone
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemovedFile {
+    pub path: PathBuf,
+    pub track_id: TrackId,
+}
+
+/// A file or directory that was skipped
+#[derive(Debug)]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    pub error: NumeroError,
+}
+
+/// What a batch run changed, each list in path order
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    /// New files, and changed files re-indexed under a new track id
+    pub added: Vec<IndexedFile>,
+    pub removed: Vec<RemovedFile>,
+    /// Files whose content is the same as when they were indexed
+    pub unchanged: Vec<PathBuf>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+/// Parallel, incremental indexer for directories of audio files
+#[derive(Debug, Clone)]
+pub struct BatchIndexer {
+    threads: usize,
+    files_in_flight: usize,
+    block_size: usize,
+}
+
+impl Default for BatchIndexer {
+    fn default() -> Self {
+        BatchIndexer {
+            threads: 0,
+            files_in_flight: 64,
+            block_size: DEFAULT_BLOCK_SIZE,
+        }
+    }
+}
+
+// A file that needs processing, with the track and checksum of its earlier version
+struct Job<'a> {
+    path: &'a PathBuf,
+    stamp: FileStamp,
+    previous: Option<TrackId>,
+    checksum: Option<u64>,
+}
+
+// A job's file as fingerprinted by a worker
+struct Fingerprinted {
+    set: FingerprintSet,
+    checksum: u64,
+    duration_secs: f64,
+}
+
+impl BatchIndexer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Worker threads; 0 uses one per CPU
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Most files fingerprinted before their results are added to the index; must be positive
+    pub fn with_files_in_flight(mut self, files: usize) -> Self {
+        self.files_in_flight = files;
+        self
+    }
+
+    /// Mono samples decoded at a time for each file; must be positive
+    pub fn with_block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size;
+        self
+    }
+
+    /// Brings the index up to date with every audio file below `dir`, and removes the tracks
+    /// of files below `dir` that no longer exist. Subdirectories that cannot be read are
+    /// skipped and reported like files; the tracks of their files are kept.
+    pub fn index_dir<F>(
+        &self,
+        dir: &Path,
+        index: &mut FingerprintIndex,
+        progress: F,
+    ) -> Result<BatchReport>
+    where
+        F: Fn(&BatchProgress) + Sync,
+    {
+        self.validate()?;
+        let dir = &fs::canonicalize(dir)?;
+        let mut unreadable = Vec::new();
+        let files = collect_audio_files(dir, &mut unreadable)?;
+        let mut stale = Vec::new();
+        let mut report = self.update(&files, index, progress, &mut stale)?;
+
+        let present: BTreeSet<&PathBuf> = files.iter().collect();
+        let gone: Vec<RemovedFile> = index
+            .manifest
+            .entries()
+            .filter(|entry| entry.path.starts_with(dir) && !present.contains(&entry.path))
+            .filter(|entry| {
+                !unreadable
+                    .iter()
+                    .any(|skipped| entry.path.starts_with(&skipped.path))
+            })
+            .map(|entry| RemovedFile {
+                path: entry.path.clone(),
+                track_id: entry.track_id,
+            })
+            .collect();
+        stale.extend(gone.iter().map(|file| file.track_id));
+        index.remove_tracks(&stale);
+
+        report.removed = gone;
+        report.skipped.extend(unreadable);
+        report.skipped.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(report)
+    }
+
+    /// Brings the index up to date with the given files, adding them in the order given.
+    /// Files that resolve to the same path are only processed once.
+    pub fn index_files<F>(
+        &self,
+        files: &[PathBuf],
+        index: &mut FingerprintIndex,
+        progress: F,
+    ) -> Result<BatchReport>
+    where
+        F: Fn(&BatchProgress) + Sync,
+    {
+        let mut stale = Vec::new();
+        let report = self.update(files, index, progress, &mut stale)?;
+        index.remove_tracks(&stale);
+        Ok(report)
+    }
+
+    // `index_files` that leaves the tracks of replaced files in the index and appends their ids
+    // to `stale`, so the caller can remove them all with one pass over the postings
+    fn update<F>(
+        &self,
+        files: &[PathBuf],
+        index: &mut FingerprintIndex,
+        progress: F,
+        stale: &mut Vec<TrackId>,
+    ) -> Result<BatchReport>
+    where
+        F: Fn(&BatchProgress) + Sync,
+    {
+        self.validate()?;
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.threads)
+            .build()
+            .map_err(|e| NumeroError::Index(format!("cannot start worker threads: {}", e)))?;
+        let mut report = BatchReport::default();
+        let files = source_paths(files, &mut report);
+        let jobs = plan_jobs(&files, index, &mut report);
+
+        let config = index.config().clone();
+        let completed = AtomicUsize::new(0);
+        for group in jobs.chunks(self.files_in_flight) {
+            let results: Vec<Result<Option<Fingerprinted>>> = pool.install(|| {
+                group
+                    .par_iter()
+                    .map(|job| {
+                        let result = self.process(job, &config);
+                        progress(&BatchProgress {
+                            completed: completed.fetch_add(1, Ordering::Relaxed) + 1,
+                            total: jobs.len(),
+                            path: job.path,
+                            outcome: match &result {
+                                Ok(Some(file)) => FileOutcome::Indexed(file.set.len()),
+                                Ok(None) => FileOutcome::Unchanged,
+                                Err(e) => FileOutcome::Failed(e),
+                            },
+                        });
+                        result
+                    })
+                    .collect()
+            });
+
+            for (job, result) in group.iter().zip(results) {
+                match result.and_then(|fingerprinted| merge(job, fingerprinted, index)) {
+                    Ok(Some(file)) => {
+                        stale.extend(file.replaced);
+                        report.added.push(file)
+                    }
+                    Ok(None) => report.unchanged.push(job.path.clone()),
+                    Err(error) => report.skipped.push(SkippedFile {
+                        path: job.path.clone(),
+                        error,
+                    }),
+                }
+            }
+        }
+
+        report.unchanged.sort();
+        report.skipped.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(report)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.files_in_flight == 0 {
+            return Err(NumeroError::InvalidConfig(
+                "files in flight must be positive".to_string(),
+            ));
+        }
+        if self.block_size == 0 {
+            return Err(NumeroError::InvalidConfig(
+                "block size must be positive".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // Fingerprints a job's file, or returns None if its content matches the earlier version.
+    // Only a file with an earlier version is read up front to compare checksums; a new one is
+    // checksummed while it is decoded.
+    fn process(&self, job: &Job, config: &FingerprintConfig) -> Result<Option<Fingerprinted>> {
+        if let Some(previous) = job.checksum {
+            if file_checksum(job.path)? == previous {
+                return Ok(None);
+            }
+        }
+
+        let (set, duration_secs, checksum) = fingerprint_source(job.path, config, self.block_size)?;
+        Ok(Some(Fingerprinted {
+            set,
+            checksum,
+            duration_secs,
+        }))
+    }
+}
+
+// The paths files are recorded under: absolute, with the directories resolved but not a link
+// to the file itself, so a deleted link is noticed. Unresolvable files are skipped.
+fn source_paths(files: &[PathBuf], report: &mut BatchReport) -> Vec<PathBuf> {
+    let mut seen = BTreeSet::new();
+    let mut paths = Vec::with_capacity(files.len());
+    for file in files {
+        let resolved = match (file.parent(), file.file_name()) {
+            (Some(parent), Some(name)) => {
+                let parent = if parent.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    parent
+                };
+                fs::canonicalize(parent).map(|parent| parent.join(name))
+            }
+            _ => fs::canonicalize(file),
+        };
+        match resolved {
+            Ok(path) => {
+                if seen.insert(path.clone()) {
+                    paths.push(path);
+                }
+            }
+            Err(e) => report.skipped.push(SkippedFile {
+                path: file.clone(),
+                error: e.into(),
+            }),
+        }
+    }
+    paths
+}
+
+// Sorts files into those the manifest shows unchanged, which go straight into the report,
+// and jobs for the rest. Files that cannot be stat'ed are skipped.
+fn plan_jobs<'a>(
+    files: &'a [PathBuf],
+    index: &FingerprintIndex,
+    report: &mut BatchReport,
+) -> Vec<Job<'a>> {
+    // Tracks without a manifest entry, by name
+    let unmanaged: HashMap<&str, TrackId> = index
+        .tracks()
+        .filter(|info| index.manifest.track_path(info.id).is_none())
+        .map(|info| (info.name.as_str(), info.id))
+        .collect();
+
+    let mut jobs = Vec::new();
+    for path in files {
+        let stamp = match FileStamp::of(path) {
+            Ok(stamp) => stamp,
+            Err(error) => {
+                report.skipped.push(SkippedFile {
+                    path: path.clone(),
+                    error,
+                });
+                continue;
+            }
+        };
+
+        match index.manifest.get(path) {
+            Some(entry) if entry.stamp == stamp => report.unchanged.push(path.clone()),
+            Some(entry) => jobs.push(Job {
+                path,
+                stamp,
+                previous: Some(entry.track_id),
+                checksum: Some(entry.checksum),
+            }),
+            None => jobs.push(Job {
+                path,
+                stamp,
+                previous: unmanaged.get(path.display().to_string().as_str()).copied(),
+                checksum: None,
+            }),
+        }
+    }
+    jobs
+}
+
+// Applies a finished job to the index. Returns the added file, or None if it was unchanged.
+// The track of an earlier version is left for the caller to remove.
+fn merge(
+    job: &Job,
+    fingerprinted: Option<Fingerprinted>,
+    index: &mut FingerprintIndex,
+) -> Result<Option<IndexedFile>> {
+    let Fingerprinted {
+        set,
+        checksum,
+        duration_secs,
+    } = match fingerprinted {
+        Some(fingerprinted) => fingerprinted,
+        None => {
+            // Only the timestamp moved; remember it so the next run need not read the file
+            if let Some(entry) = index.manifest.get(job.path) {
+                let entry = ManifestEntry {
+                    stamp: job.stamp,
+                    ..entry.clone()
+                };
+                index.manifest.insert(entry);
+            }
+            return Ok(None);
+        }
+    };
+
+    let track_id = index.add_track(&job.path.display().to_string(), &set)?;
+    index.manifest.insert(ManifestEntry {
+        path: job.path.clone(),
+        track_id,
+        stamp: job.stamp,
+        checksum,
+        duration_secs,
+    });
+
+    Ok(Some(IndexedFile {
+        path: job.path.clone(),
+        track_id,
+        fingerprints: set.len(),
+        duration_secs,
+        replaced: job.previous,
+    }))
+}
+
+/// Whether a path has one of the `AUDIO_EXTENSIONS`
+pub fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| AUDIO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+// Recursively collects audio files below `dir`, sorted by path. Symbolic links to directories
+// are not followed. Only an unreadable `dir` itself is an error; unreadable entries below it
+// are recorded in `skipped`.
+fn collect_audio_files(dir: &Path, skipped: &mut Vec<SkippedFile>) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(current) = pending.pop() {
+        let entries = match fs::read_dir(&current) {
+            Ok(entries) => entries,
+            Err(e) if current == dir => return Err(e.into()),
+            Err(e) => {
+                skipped.push(SkippedFile {
+                    path: current,
+                    error: e.into(),
+                });
+                continue;
+            }
+        };
+        for entry in entries {
+            let (path, file_type) = match entry.and_then(|e| Ok((e.path(), e.file_type()?))) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    skipped.push(SkippedFile {
+                        path: current.clone(),
+                        error: e.into(),
+                    });
+                    continue;
+                }
+            };
+            // Links are only followed to files, so a link to a parent cannot loop the walk
+            let is_file = file_type.is_file()
+                || (file_type.is_symlink() && fs::metadata(&path).is_ok_and(|m| m.is_file()));
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if is_file && is_audio_file(&path) {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
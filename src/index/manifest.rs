@@ -0,0 +1,165 @@
+// Source manifest
+// Records which file every indexed track came from and what that file looked like when it was
+// fingerprinted, so a later run over the same directory only has to process what changed.
+// A file whose size and modification time match its entry is taken as unchanged without
+// reading it; otherwise its content checksum decides. The fingerprint parameters are those of
+// the index itself, which every entry was produced with; the store header keeps them.
+
+use super::TrackId;
+use crate::error::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Bytes read at a time while checksumming
+const CHECKSUM_BLOCK_SIZE: usize = 1 << 16;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// What an indexed source file looked like when it was fingerprinted
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    pub path: PathBuf,
+    pub track_id: TrackId,
+    pub stamp: FileStamp,
+    /// 64-bit FNV-1a hash of the file's bytes
+    pub checksum: u64,
+    /// Length of the decoded audio in seconds
+    pub duration_secs: f64,
+}
+
+/// Size and modification time of a file, the cheap part of change detection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    pub size: u64,
+    /// Nanoseconds since the Unix epoch, 0 where the platform does not report it
+    pub modified_nanos: u64,
+}
+
+impl FileStamp {
+    pub fn of(path: &Path) -> Result<Self> {
+        let metadata = fs::metadata(path)?;
+        let modified_nanos = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|since| since.as_nanos() as u64)
+            .unwrap_or(0);
+
+        Ok(FileStamp {
+            size: metadata.len(),
+            modified_nanos,
+        })
+    }
+}
+
+/// Indexed source files by path, and by track
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Manifest {
+    entries: BTreeMap<PathBuf, ManifestEntry>,
+    paths: HashMap<TrackId, PathBuf>,
+}
+
+impl Manifest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&ManifestEntry> {
+        self.entries.get(path)
+    }
+
+    /// Records an entry, returning the one it replaces for the same path. An entry for the same
+    /// track under another path is dropped.
+    pub fn insert(&mut self, entry: ManifestEntry) -> Option<ManifestEntry> {
+        let track_id = entry.track_id;
+        if let Some(path) = self.paths.insert(track_id, entry.path.clone()) {
+            if path != entry.path {
+                self.entries.remove(&path);
+            }
+        }
+
+        let replaced = self.entries.insert(entry.path.clone(), entry)?;
+        if replaced.track_id != track_id {
+            self.paths.remove(&replaced.track_id);
+        }
+        Some(replaced)
+    }
+
+    pub fn remove(&mut self, path: &Path) -> Option<ManifestEntry> {
+        let entry = self.entries.remove(path)?;
+        self.paths.remove(&entry.track_id);
+        Some(entry)
+    }
+
+    /// Drops the entry of a track, if it has one
+    pub fn remove_track(&mut self, track_id: TrackId) -> Option<ManifestEntry> {
+        let path = self.paths.remove(&track_id)?;
+        self.entries.remove(&path)
+    }
+
+    /// The source file a track was indexed from
+    pub fn track_path(&self, track_id: TrackId) -> Option<&Path> {
+        self.paths.get(&track_id).map(PathBuf::as_path)
+    }
+
+    /// Iterates over all entries in path order
+    pub fn entries(&self) -> impl Iterator<Item = &ManifestEntry> {
+        self.entries.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// 64-bit FNV-1a hash of a file's contents, read in blocks
+pub fn file_checksum(path: &Path) -> Result<u64> {
+    let mut checksum = Checksum::new();
+    checksum.update_from(BufReader::new(File::open(path)?))?;
+    Ok(checksum.value())
+}
+
+/// Incremental form of `file_checksum`, for bytes that arrive in pieces
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Checksum {
+    hash: u64,
+}
+
+impl Checksum {
+    pub fn new() -> Self {
+        Checksum {
+            hash: FNV_OFFSET_BASIS,
+        }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.hash ^= byte as u64;
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    /// Adds everything left in `reader`
+    pub fn update_from<R: Read>(&mut self, mut reader: R) -> Result<()> {
+        let mut buffer = vec![0u8; CHECKSUM_BLOCK_SIZE];
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                return Ok(());
+            }
+            self.update(&buffer[..read]);
+        }
+    }
+
+    pub fn value(&self) -> u64 {
+        self.hash
+    }
+}
@@ -0,0 +1,257 @@
+// Fingerprint database
+// An inverted index from hash to every (track, anchor frame) it occurs at, so a clip can be
+// identified against many reference tracks with one lookup per clip hash instead of a
+// linear scan over every song.
+
+pub mod batch;
+pub mod manifest;
+pub mod store;
+
+use crate::error::{NumeroError, Result};
+use crate::fingerprint::calibration::Calibration;
+use crate::fingerprint::config::{FingerprintConfig, HashFamily};
+use crate::fingerprint::fingerprint::FingerprintSet;
+use crate::fingerprint::matcher::OffsetHistogram;
+use manifest::Manifest;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Identifier assigned to a track when it is added to the index
+pub type TrackId = u32;
+
+/// A single occurrence of a hash inside an indexed track
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Posting {
+    pub track_id: TrackId,
+    pub anchor_frame: u32,
+}
+
+/// Metadata kept for every indexed track
+#[derive(Debug, Clone)]
+pub struct TrackInfo {
+    pub id: TrackId,
+    pub name: String,
+    pub fingerprint_count: usize,
+}
+
+/// A candidate track for a query, with the alignment that collected the most votes.
+/// `FingerprintIndex::query` returns every track that shares a hash with the clip, so callers
+/// can show the alternatives and apply their own thresholds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchResult {
+    pub track_id: TrackId,
+    pub name: String,
+    /// Offset of the clip into the track, in frames
+    pub offset_frames: i64,
+    /// Offset of the clip into the track, in seconds
+    pub offset_secs: f64,
+    /// Stretch of the clip between the first and last hash that agree on the offset, in seconds
+    pub covered_secs: f64,
+    /// Number of clip hashes that agree on this offset
+    pub votes: usize,
+    /// Fraction of the clip's hashes that agree on this offset, from 0 to 1
+    pub score: f64,
+    /// Standard deviations by which `votes` exceeds the track's other offsets; compare it with
+    /// a `Calibration` threshold
+    pub significance: f64,
+    /// Clip hashes found in the track at any offset
+    pub matched_hashes: usize,
+}
+
+/// Inverted index mapping hash -> list of (track_id, anchor_time).
+/// All tracks in an index share one fingerprint config. Tracks indexed from files by
+/// `BatchIndexer` also have an entry in the manifest.
+/// Votes assume clip and track run at the same speed, so only pair hashes can be indexed;
+/// triplet fingerprints are matched with `find_scaled_match` instead.
+#[derive(Debug, Default)]
+pub struct FingerprintIndex {
+    config: FingerprintConfig,
+    postings: HashMap<u32, Vec<Posting>>,
+    tracks: BTreeMap<TrackId, TrackInfo>,
+    next_id: TrackId,
+    manifest: Manifest,
+}
+
+impl FingerprintIndex {
+    pub fn new(config: FingerprintConfig) -> Self {
+        FingerprintIndex {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// The config every track in this index was fingerprinted with
+    pub fn config(&self) -> &FingerprintConfig {
+        &self.config
+    }
+
+    /// Adds a track's fingerprints to the index and returns its new id
+    pub fn add_track(&mut self, name: &str, set: &FingerprintSet) -> Result<TrackId> {
+        self.check_config(set)?;
+
+        let track_id = self.next_id;
+        self.next_id += 1;
+
+        for fp in &set.fingerprints {
+            self.postings.entry(fp.hash).or_default().push(Posting {
+                track_id,
+                anchor_frame: fp.anchor_frame as u32,
+            });
+        }
+
+        self.tracks.insert(
+            track_id,
+            TrackInfo {
+                id: track_id,
+                name: name.to_string(),
+                fingerprint_count: set.len(),
+            },
+        );
+
+        Ok(track_id)
+    }
+
+    /// Removes a track, its postings and its manifest entry. Returns the removed track's info,
+    /// if any.
+    pub fn remove_track(&mut self, track_id: TrackId) -> Option<TrackInfo> {
+        self.remove_tracks(&[track_id]).pop()
+    }
+
+    /// Removes several tracks with one pass over the postings. Returns the info of each track
+    /// that was present.
+    pub fn remove_tracks(&mut self, track_ids: &[TrackId]) -> Vec<TrackInfo> {
+        let removed: Vec<TrackInfo> = track_ids
+            .iter()
+            .filter_map(|track_id| self.tracks.remove(track_id))
+            .collect();
+        if removed.is_empty() {
+            return removed;
+        }
+
+        let ids: HashSet<TrackId> = removed.iter().map(|info| info.id).collect();
+        for &track_id in &ids {
+            self.manifest.remove_track(track_id);
+        }
+        self.postings.retain(|_, postings| {
+            postings.retain(|p| !ids.contains(&p.track_id));
+            !postings.is_empty()
+        });
+
+        removed
+    }
+
+    /// Looks up every clip hash and votes for (track, offset) alignments.
+    /// Returns one candidate per track, ranked by votes (best first).
+    pub fn query(&self, clip: &FingerprintSet) -> Result<Vec<MatchResult>> {
+        self.check_config(clip)?;
+
+        let mut histograms: HashMap<TrackId, OffsetHistogram> = HashMap::new();
+        // Clip hashes found in each track, however often they occur there
+        let mut matched: HashMap<TrackId, usize> = HashMap::new();
+        let mut hit_tracks = Vec::new();
+
+        for fp in &clip.fingerprints {
+            if let Some(postings) = self.postings.get(&fp.hash) {
+                hit_tracks.clear();
+                for posting in postings {
+                    histograms
+                        .entry(posting.track_id)
+                        .or_default()
+                        .vote(posting.anchor_frame as usize, fp.anchor_frame);
+                    hit_tracks.push(posting.track_id);
+                }
+                hit_tracks.sort_unstable();
+                hit_tracks.dedup();
+                for &track_id in &hit_tracks {
+                    *matched.entry(track_id).or_insert(0) += 1;
+                }
+            }
+        }
+
+        // Keep the best offset for each track
+        let time_base = self.config.time_base();
+        let mut matches: Vec<MatchResult> = histograms
+            .into_iter()
+            .filter_map(|(track_id, histogram)| {
+                let (offset_frames, votes) = histogram.best()?;
+                let bin = histogram.bin(offset_frames)?;
+                let covered_frames = bin.last_clip_frame - bin.first_clip_frame + 1;
+                self.tracks.get(&track_id).map(|info| MatchResult {
+                    track_id,
+                    name: info.name.clone(),
+                    offset_frames,
+                    offset_secs: time_base.to_secs(offset_frames as f64),
+                    covered_secs: time_base.to_secs(covered_frames as f64),
+                    votes,
+                    score: (votes as f64 / clip.len() as f64).min(1.0),
+                    significance: histogram.significance(offset_frames),
+                    matched_hashes: matched.get(&track_id).copied().unwrap_or(0),
+                })
+            })
+            .collect();
+
+        matches.sort_by(|a, b| b.votes.cmp(&a.votes).then(a.track_id.cmp(&b.track_id)));
+        Ok(matches)
+    }
+
+    /// Calibrates a significance threshold from clips known not to be in the index.
+    /// Each negative contributes the highest significance any track reaches for it, so the
+    /// threshold bounds the rate at which an unknown clip's best candidate is a false match.
+    pub fn calibrate(
+        &self,
+        negatives: &[FingerprintSet],
+        target_false_positive_rate: f64,
+    ) -> Result<Calibration> {
+        let mut scores = Vec::with_capacity(negatives.len());
+        for clip in negatives {
+            let best = self
+                .query(clip)?
+                .iter()
+                .map(|m| m.significance)
+                .fold(0.0, f64::max);
+            scores.push(best);
+        }
+        Calibration::from_negative_scores(&scores, target_false_positive_rate)
+    }
+
+    /// Returns the info for a single track
+    pub fn track(&self, track_id: TrackId) -> Option<&TrackInfo> {
+        self.tracks.get(&track_id)
+    }
+
+    /// Iterates over all indexed tracks in id order
+    pub fn tracks(&self) -> impl Iterator<Item = &TrackInfo> {
+        self.tracks.values()
+    }
+
+    /// The source files of the tracks added by `BatchIndexer`
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    pub fn track_count(&self) -> usize {
+        self.tracks.len()
+    }
+
+    /// Number of distinct hashes in the index
+    pub fn hash_count(&self) -> usize {
+        self.postings.len()
+    }
+
+    /// Hashes are only comparable when produced by the same pipeline parameters
+    fn check_config(&self, set: &FingerprintSet) -> Result<()> {
+        if set.config != self.config {
+            return Err(NumeroError::ConfigMismatch(format!(
+                "index uses {:?}, got {:?}",
+                self.config, set.config
+            )));
+        }
+        if self.config.hash_family != HashFamily::Pair {
+            return Err(NumeroError::InvalidConfig(format!(
+                "the index cannot match {} hashes, which need the anchor bins kept by \
+                 find_scaled_match",
+                self.config.hash_family.name()
+            )));
+        }
+        Ok(())
+    }
+}
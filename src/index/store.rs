@@ -0,0 +1,586 @@
+// On-disk fingerprint store
+// A compact little-endian binary format for fingerprint lists and whole indexes.
+//
+// Layout:
+// - header: magic "NUMR", format version (u16), payload kind (u8), fingerprint config
+// - fingerprints payload: count (u64), then (hash u32, anchor frame u32) per record
+// - index payload: next track id (u32), track table, one posting list per hash, then the
+//   source manifest, whose paths are kept as the platform's raw bytes rather than as text
+//
+// The stored config is checked against the caller's on load so hashes produced with different
+// settings are never mixed.
+
+use super::manifest::{FileStamp, ManifestEntry};
+use super::{FingerprintIndex, Posting, TrackInfo};
+use crate::error::{NumeroError, Result};
+use crate::fingerprint::config::{
+    BandLayout, FingerprintConfig, HashFamily, Normalization, Pairing, PeakPicker,
+};
+use crate::fingerprint::fingerprint::FingerprintSet;
+use crate::fingerprint::hash::{Fingerprint, HashCodec};
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+pub const MAGIC: [u8; 4] = *b"NUMR";
+pub const FORMAT_VERSION: u16 = 2;
+
+/// Most records reserved up front from a count read from the file; larger collections grow as
+/// they are read, so a corrupt count fails at the end of the data instead of exhausting memory
+const MAX_PREALLOCATED: usize = 1 << 20;
+
+/// What a store file contains after its header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreKind {
+    Fingerprints,
+    Index,
+}
+
+impl StoreKind {
+    fn to_byte(self) -> u8 {
+        match self {
+            StoreKind::Fingerprints => 1,
+            StoreKind::Index => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self> {
+        match byte {
+            1 => Ok(StoreKind::Fingerprints),
+            2 => Ok(StoreKind::Index),
+            other => Err(NumeroError::Store(format!("Unknown store kind: {}", other))),
+        }
+    }
+}
+
+fn write_config<W: Write>(writer: &mut W, config: &FingerprintConfig) -> Result<()> {
+    write_u32(writer, config.target_sample_rate)?;
+    write_u32(writer, config.filter_taps as u32)?;
+    write_u32(writer, config.frame_size as u32)?;
+    write_u32(writer, config.hop_size as u32)?;
+    write_u32(writer, config.num_bands as u32)?;
+    write_u32(writer, config.target_zone_frames as u32)?;
+    write_f64(writer, config.threshold_multiplier)?;
+    write_u8(writer, normalization_to_byte(config.normalization))?;
+    match config.peak_picker {
+        PeakPicker::BandMax => write_u8(writer, 0)?,
+        PeakPicker::Constellation {
+            neighborhood_frames,
+            neighborhood_bins,
+            peaks_per_second,
+        } => {
+            write_u8(writer, 1)?;
+            write_u32(writer, neighborhood_frames as u32)?;
+            write_u32(writer, neighborhood_bins as u32)?;
+            write_f64(writer, peaks_per_second)?;
+        }
+    }
+    match &config.band_layout {
+        BandLayout::Linear => write_u8(writer, 0)?,
+        BandLayout::Log => write_u8(writer, 1)?,
+        BandLayout::Bark => write_u8(writer, 2)?,
+        BandLayout::Mel => write_u8(writer, 3)?,
+        BandLayout::Custom(edges) => {
+            write_u8(writer, 4)?;
+            write_u32(writer, edges.len() as u32)?;
+            for &edge in edges {
+                write_f64(writer, edge)?;
+            }
+        }
+    }
+    write_u8(writer, config.hash_codec.freq_bits as u8)?;
+    write_u8(writer, config.hash_codec.dt_bits as u8)?;
+    write_u32(writer, config.hash_codec.freq_step)?;
+    write_optional_u32(writer, config.pairing.max_fan_out)?;
+    write_u32(writer, config.pairing.min_dt as u32)?;
+    write_optional_u32(writer, config.pairing.max_freq_distance)?;
+    write_u8(writer, config.pairing.strongest_first as u8)?;
+    write_u8(
+        writer,
+        match config.hash_family {
+            HashFamily::Pair => 0,
+            HashFamily::Triplet => 1,
+        },
+    )?;
+    Ok(())
+}
+
+// A presence byte, followed by the value when present
+fn write_optional_u32<W: Write>(writer: &mut W, value: Option<usize>) -> Result<()> {
+    match value {
+        Some(value) => {
+            write_u8(writer, 1)?;
+            write_u32(writer, value as u32)?;
+        }
+        None => write_u8(writer, 0)?,
+    }
+    Ok(())
+}
+
+fn read_optional_u32<R: Read>(reader: &mut R) -> Result<Option<usize>> {
+    match read_u8(reader)? {
+        0 => Ok(None),
+        1 => Ok(Some(read_u32(reader)? as usize)),
+        other => Err(NumeroError::Store(format!(
+            "Invalid optional value flag: {}",
+            other
+        ))),
+    }
+}
+
+fn read_pairing<R: Read>(reader: &mut R) -> Result<Pairing> {
+    Ok(Pairing {
+        max_fan_out: read_optional_u32(reader)?,
+        min_dt: read_u32(reader)? as usize,
+        max_freq_distance: read_optional_u32(reader)?,
+        strongest_first: read_u8(reader)? != 0,
+    })
+}
+
+fn read_config<R: Read>(reader: &mut R) -> Result<FingerprintConfig> {
+    Ok(FingerprintConfig {
+        target_sample_rate: read_u32(reader)?,
+        filter_taps: read_u32(reader)? as usize,
+        frame_size: read_u32(reader)? as usize,
+        hop_size: read_u32(reader)? as usize,
+        num_bands: read_u32(reader)? as usize,
+        target_zone_frames: read_u32(reader)? as usize,
+        threshold_multiplier: read_f64(reader)?,
+        normalization: normalization_from_byte(read_u8(reader)?)?,
+        peak_picker: read_peak_picker(reader)?,
+        band_layout: read_band_layout(reader)?,
+        hash_codec: HashCodec {
+            freq_bits: read_u8(reader)? as u32,
+            dt_bits: read_u8(reader)? as u32,
+            freq_step: read_u32(reader)?,
+        },
+        pairing: read_pairing(reader)?,
+        hash_family: read_hash_family(reader)?,
+    })
+}
+
+fn read_band_layout<R: Read>(reader: &mut R) -> Result<BandLayout> {
+    match read_u8(reader)? {
+        0 => Ok(BandLayout::Linear),
+        1 => Ok(BandLayout::Log),
+        2 => Ok(BandLayout::Bark),
+        3 => Ok(BandLayout::Mel),
+        4 => {
+            let count = read_u32(reader)? as usize;
+            let mut edges = Vec::with_capacity(count.min(1024));
+            for _ in 0..count {
+                edges.push(read_f64(reader)?);
+            }
+            Ok(BandLayout::Custom(edges))
+        }
+        other => Err(NumeroError::Store(format!(
+            "Unknown band layout: {}",
+            other
+        ))),
+    }
+}
+
+fn read_hash_family<R: Read>(reader: &mut R) -> Result<HashFamily> {
+    match read_u8(reader)? {
+        0 => Ok(HashFamily::Pair),
+        1 => Ok(HashFamily::Triplet),
+        other => Err(NumeroError::Store(format!(
+            "Unknown hash family: {}",
+            other
+        ))),
+    }
+}
+
+fn read_peak_picker<R: Read>(reader: &mut R) -> Result<PeakPicker> {
+    match read_u8(reader)? {
+        0 => Ok(PeakPicker::BandMax),
+        1 => Ok(PeakPicker::Constellation {
+            neighborhood_frames: read_u32(reader)? as usize,
+            neighborhood_bins: read_u32(reader)? as usize,
+            peaks_per_second: read_f64(reader)?,
+        }),
+        other => Err(NumeroError::Store(format!(
+            "Unknown peak picker: {}",
+            other
+        ))),
+    }
+}
+
+fn normalization_to_byte(normalization: Normalization) -> u8 {
+    match normalization {
+        Normalization::None => 0,
+        Normalization::Peak => 1,
+        Normalization::Rms => 2,
+        Normalization::PerFrame => 3,
+        Normalization::LogMagnitude => 4,
+    }
+}
+
+fn normalization_from_byte(byte: u8) -> Result<Normalization> {
+    match byte {
+        0 => Ok(Normalization::None),
+        1 => Ok(Normalization::Peak),
+        2 => Ok(Normalization::Rms),
+        3 => Ok(Normalization::PerFrame),
+        4 => Ok(Normalization::LogMagnitude),
+        other => Err(NumeroError::Store(format!(
+            "Unknown normalization: {}",
+            other
+        ))),
+    }
+}
+
+/// The fixed-size header at the start of every store file
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoreHeader {
+    pub version: u16,
+    pub kind: StoreKind,
+    pub config: FingerprintConfig,
+}
+
+/// Reads and validates the magic and version of a store, without checking its config
+pub fn read_header<R: Read>(reader: &mut R) -> Result<StoreHeader> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(NumeroError::Store("Not a numero store file".to_string()));
+    }
+
+    let version = read_u16(reader)?;
+    if version != FORMAT_VERSION {
+        return Err(NumeroError::Store(format!(
+            "Unsupported store version: {} (expected {})",
+            version, FORMAT_VERSION
+        )));
+    }
+
+    let kind = StoreKind::from_byte(read_u8(reader)?)?;
+    let config = read_config(reader)?;
+
+    Ok(StoreHeader {
+        version,
+        kind,
+        config,
+    })
+}
+
+fn write_header<W: Write>(
+    writer: &mut W,
+    kind: StoreKind,
+    config: &FingerprintConfig,
+) -> Result<()> {
+    writer.write_all(&MAGIC)?;
+    write_u16(writer, FORMAT_VERSION)?;
+    write_u8(writer, kind.to_byte())?;
+    write_config(writer, config)
+}
+
+/// Reads the header and rejects stores of another kind or built with another config
+fn read_checked_header<R: Read>(
+    reader: &mut R,
+    kind: StoreKind,
+    expected: &FingerprintConfig,
+) -> Result<StoreHeader> {
+    let header = read_header(reader)?;
+
+    if header.kind != kind {
+        return Err(NumeroError::Store(format!(
+            "Expected a {:?} store, found {:?}",
+            kind, header.kind
+        )));
+    }
+
+    if header.config != *expected {
+        return Err(NumeroError::ConfigMismatch(format!(
+            "store has {:?}, expected {:?}",
+            header.config, expected
+        )));
+    }
+
+    Ok(header)
+}
+
+/// Writes a fingerprint set with a header
+pub fn write_fingerprints<W: Write>(writer: &mut W, set: &FingerprintSet) -> Result<()> {
+    write_header(writer, StoreKind::Fingerprints, &set.config)?;
+    write_u64(writer, set.len() as u64)?;
+
+    for fp in &set.fingerprints {
+        write_u32(writer, fp.hash)?;
+        write_u32(writer, fp.anchor_frame as u32)?;
+    }
+
+    Ok(())
+}
+
+/// Reads a fingerprint set written by `write_fingerprints`
+pub fn read_fingerprints<R: Read>(
+    reader: &mut R,
+    expected: &FingerprintConfig,
+) -> Result<FingerprintSet> {
+    let header = read_checked_header(reader, StoreKind::Fingerprints, expected)?;
+    let count = read_u64(reader)? as usize;
+
+    let mut fingerprints = Vec::with_capacity(count.min(MAX_PREALLOCATED));
+    for _ in 0..count {
+        fingerprints.push(Fingerprint {
+            hash: read_u32(reader)?,
+            anchor_frame: read_u32(reader)? as usize,
+        });
+    }
+
+    Ok(FingerprintSet {
+        config: header.config,
+        fingerprints,
+    })
+}
+
+/// Writes a whole index (track table and posting lists) with a header
+pub fn write_index<W: Write>(writer: &mut W, index: &FingerprintIndex) -> Result<()> {
+    write_header(writer, StoreKind::Index, &index.config)?;
+    write_u32(writer, index.next_id)?;
+
+    write_u32(writer, index.tracks.len() as u32)?;
+    for info in index.tracks.values() {
+        write_u32(writer, info.id)?;
+        write_string(writer, &info.name)?;
+        write_u64(writer, info.fingerprint_count as u64)?;
+    }
+
+    write_u64(writer, index.postings.len() as u64)?;
+    for (&hash, postings) in &index.postings {
+        write_u32(writer, hash)?;
+        write_u32(writer, postings.len() as u32)?;
+        for posting in postings {
+            write_u32(writer, posting.track_id)?;
+            write_u32(writer, posting.anchor_frame)?;
+        }
+    }
+
+    write_u32(writer, index.manifest.len() as u32)?;
+    for entry in index.manifest.entries() {
+        write_path(writer, &entry.path)?;
+        write_u32(writer, entry.track_id)?;
+        write_u64(writer, entry.stamp.size)?;
+        write_u64(writer, entry.stamp.modified_nanos)?;
+        write_u64(writer, entry.checksum)?;
+        write_f64(writer, entry.duration_secs)?;
+    }
+
+    Ok(())
+}
+
+/// Reads an index written by `write_index`
+pub fn read_index<R: Read>(
+    reader: &mut R,
+    expected: &FingerprintConfig,
+) -> Result<FingerprintIndex> {
+    let header = read_checked_header(reader, StoreKind::Index, expected)?;
+
+    let mut index = FingerprintIndex::new(header.config);
+    index.next_id = read_u32(reader)?;
+
+    let track_count = read_u32(reader)?;
+    for _ in 0..track_count {
+        let id = read_u32(reader)?;
+        let name = read_string(reader)?;
+        let fingerprint_count = read_u64(reader)? as usize;
+        index.tracks.insert(
+            id,
+            TrackInfo {
+                id,
+                name,
+                fingerprint_count,
+            },
+        );
+    }
+
+    let hash_count = read_u64(reader)?;
+    for _ in 0..hash_count {
+        let hash = read_u32(reader)?;
+        let posting_count = read_u32(reader)? as usize;
+
+        let mut postings = Vec::with_capacity(posting_count.min(MAX_PREALLOCATED));
+        for _ in 0..posting_count {
+            postings.push(Posting {
+                track_id: read_u32(reader)?,
+                anchor_frame: read_u32(reader)?,
+            });
+        }
+        index.postings.insert(hash, postings);
+    }
+
+    let entry_count = read_u32(reader)?;
+    for _ in 0..entry_count {
+        index.manifest.insert(ManifestEntry {
+            path: read_path(reader)?,
+            track_id: read_u32(reader)?,
+            stamp: FileStamp {
+                size: read_u64(reader)?,
+                modified_nanos: read_u64(reader)?,
+            },
+            checksum: read_u64(reader)?,
+            duration_secs: read_f64(reader)?,
+        });
+    }
+
+    Ok(index)
+}
+
+/// Saves a fingerprint set to a file
+pub fn save_fingerprints(path: impl AsRef<Path>, set: &FingerprintSet) -> Result<()> {
+    save_replacing(path.as_ref(), |writer| write_fingerprints(writer, set))
+}
+
+// Writes a store to a temporary file next to `path` and renames it over `path` once it is
+// complete and on disk, so a failed or interrupted save leaves the previous file intact
+fn save_replacing<F>(path: &Path, write: F) -> Result<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<()>,
+{
+    let name = path.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "store path has no file name")
+    })?;
+    let mut temp_name = OsString::from(".");
+    temp_name.push(name);
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp = path.with_file_name(temp_name);
+
+    let result = (|| {
+        let mut writer = BufWriter::new(File::create(&temp)?);
+        write(&mut writer)?;
+        let file = writer.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        fs::rename(&temp, path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Loads a fingerprint set from a file, rejecting it if it was built with another config
+pub fn load_fingerprints(
+    path: impl AsRef<Path>,
+    expected: &FingerprintConfig,
+) -> Result<FingerprintSet> {
+    let mut reader = BufReader::new(File::open(path)?);
+    read_fingerprints(&mut reader, expected)
+}
+
+/// Reads just the header of a store file
+pub fn inspect_store(path: impl AsRef<Path>) -> Result<StoreHeader> {
+    let mut reader = BufReader::new(File::open(path)?);
+    read_header(&mut reader)
+}
+
+impl FingerprintIndex {
+    /// Saves the index to a file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        save_replacing(path.as_ref(), |writer| write_index(writer, self))
+    }
+
+    /// Loads an index from a file, rejecting it if it was built with another config
+    pub fn load(path: impl AsRef<Path>, expected: &FingerprintConfig) -> Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        read_index(&mut reader, expected)
+    }
+}
+
+fn write_u8<W: Write>(writer: &mut W, value: u8) -> io::Result<()> {
+    writer.write_all(&[value])
+}
+
+fn write_u16<W: Write>(writer: &mut W, value: u16) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_u32<W: Write>(writer: &mut W, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_u64<W: Write>(writer: &mut W, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_f64<W: Write>(writer: &mut W, value: f64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_string<W: Write>(writer: &mut W, value: &str) -> io::Result<()> {
+    write_bytes(writer, value.as_bytes())
+}
+
+fn write_path<W: Write>(writer: &mut W, path: &Path) -> io::Result<()> {
+    write_bytes(writer, path.as_os_str().as_encoded_bytes())
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    write_u32(writer, bytes.len() as u32)?;
+    writer.write_all(bytes)
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> io::Result<u16> {
+    let mut buf = [0u8; 2];
+    reader.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_f64<R: Read>(reader: &mut R) -> io::Result<f64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(f64::from_le_bytes(buf))
+}
+
+fn read_string<R: Read>(reader: &mut R) -> Result<String> {
+    String::from_utf8(read_bytes(reader)?)
+        .map_err(|e| NumeroError::Store(format!("Invalid string: {}", e)))
+}
+
+// Paths are not necessarily valid UTF-8, so they round-trip as bytes wherever the platform can
+// rebuild them from bytes
+#[cfg(unix)]
+fn read_path<R: Read>(reader: &mut R) -> Result<PathBuf> {
+    use std::os::unix::ffi::OsStringExt;
+    Ok(PathBuf::from(std::ffi::OsString::from_vec(read_bytes(
+        reader,
+    )?)))
+}
+
+#[cfg(not(unix))]
+fn read_path<R: Read>(reader: &mut R) -> Result<PathBuf> {
+    String::from_utf8(read_bytes(reader)?)
+        .map(PathBuf::from)
+        .map_err(|e| NumeroError::Store(format!("Invalid path: {}", e)))
+}
+
+fn read_bytes<R: Read>(reader: &mut R) -> Result<Vec<u8>> {
+    let len = read_u32(reader)? as u64;
+    // Grows with the bytes actually present rather than trusting the length
+    let mut buf = Vec::new();
+    reader.take(len).read_to_end(&mut buf)?;
+    if buf.len() as u64 != len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(buf)
+}
@@ -1 +1,30 @@
- 
\ No newline at end of file
+//! Numero - audio fingerprinting and matching.
+//!
+//! The crate exposes the full pipeline used by the `numero` binary: decoding audio
+//! into mono samples, turning those samples into fingerprint hashes and matching a
+//! clip's hashes against a reference track or an index of many tracks.
+
+pub mod dsp;
+pub mod error;
+pub mod fingerprint;
+pub mod index;
+pub mod utils;
+pub mod wav;
+
+// Re-export the main pipeline for easier access
+pub use error::{NumeroError, Result};
+pub use fingerprint::hash::{hash_fingerprint, Fingerprint, HashCodec};
+pub use fingerprint::matcher::{find_match, find_scaled_match, OffsetMatch, ScaledMatch};
+pub use fingerprint::peaks::Peak;
+pub use fingerprint::{
+    finger_print, finger_print_blocks, finger_print_triplets, match_fingerprints, BandLayout,
+    Calibration, FingerprintConfig, FingerprintSet, HashFamily, Normalization, Pairing, PeakPicker,
+    StreamingFingerprinter, TimeBase, TripletFingerprint,
+};
+pub use index::batch::{
+    BatchIndexer, BatchProgress, BatchReport, FileOutcome, IndexedFile, RemovedFile, SkippedFile,
+};
+pub use index::manifest::{FileStamp, Manifest, ManifestEntry};
+pub use index::store::{load_fingerprints, save_fingerprints};
+pub use index::{FingerprintIndex, MatchResult, TrackId};
+pub use wav::{decode_audio_blocks, open_audio_blocks, read_audio_file, AudioBlocks};
@@ -1,4 +1,6 @@
-/// Audio processing utility functions
+//! Audio processing utility functions
+
+use crate::error::{NumeroError, Result};
 
 /// Safely convert i16 to absolute value as f32, handling MIN_VALUE case
 pub fn safe_abs(x: i16) -> f32 {
@@ -10,50 +12,58 @@ pub fn safe_abs(x: i16) -> f32 {
 }
 
 /// Validates that the audio data meets our format requirements
-pub fn validate_audio_format(samples: &[i16], sample_rate: u32) -> Result<(), String> {
+pub fn validate_audio_format(samples: &[i16], sample_rate: u32) -> Result<()> {
     // Check if we have any samples
     if samples.is_empty() {
-        return Err("No audio samples found".to_string());
-    }
-
-    // Verify sample rate is standard
-    if ![44100, 48000, 96000].contains(&sample_rate) {
-        return Err(format!("Unusual sample rate: {} Hz", sample_rate));
+        return Err(NumeroError::EmptyInput);
     }
 
-    // Verify samples are within 16-bit range
-    if let (Some(&min), Some(&max)) = (samples.iter().min(), samples.iter().max()) {
-        if min < -32768 || max > 32767 {
-            return Err(format!(
-                "Samples outside 16-bit range: min={}, max={}",
-                min, max
-            ));
-        }
-    }
+    // Verify the sample rate is usable
+    validate_sample_rate(sample_rate)?;
 
     // Calculate average absolute difference between consecutive samples
     // This can help detect if we're truly mono (should have smooth transitions)
-    let avg_diff: f32 = samples
+    let total_variation: f32 = samples
         .windows(2)
-        .map(|w| {
-            let a = safe_abs(w[0]);
-            let b = safe_abs(w[1]);
-            (b - a).abs()
-        })
-        .sum::<f32>()
-        / (samples.len() - 1) as f32;
+        .map(|w| sample_variation(w[0], w[1]))
+        .sum();
+
+    check_sample_variation(total_variation, samples.len())
+}
+
+/// Difference in absolute level between consecutive samples
+pub fn sample_variation(previous: i16, next: i16) -> f32 {
+    (safe_abs(next) - safe_abs(previous)).abs()
+}
+
+/// Rejects a signal of `samples` samples whose `sample_variation` adds up to `total_variation`
+/// if it changes level too much between samples to be a properly downmixed recording
+pub fn check_sample_variation(total_variation: f32, samples: usize) -> Result<()> {
+    let avg_diff = total_variation / (samples - 1) as f32;
 
     // If average difference is too high, might indicate incorrect channel mixing
     if avg_diff > 10000.0 {
-        return Err(format!(
+        return Err(NumeroError::InvalidAudio(format!(
             "Unusually high sample variation (avg_diff={}), possible stereo mixing issue",
             avg_diff
-        ));
+        )));
     }
 
     Ok(())
 }
 
+/// Checks that the decoder reported a usable sample rate. The resampler converts any rate;
+/// whether it is high enough depends on the fingerprint config and is checked there.
+pub fn validate_sample_rate(sample_rate: u32) -> Result<()> {
+    if sample_rate == 0 {
+        return Err(NumeroError::UnsupportedSampleRate {
+            sample_rate,
+            reason: "expected a positive rate".to_string(),
+        });
+    }
+    Ok(())
+}
+
 /// Calculate audio statistics for a given sample buffer
 pub fn calculate_audio_stats(samples: &[i16]) -> AudioStats {
     let (min, max) = samples.iter().fold((i16::MAX, i16::MIN), |(min, max), &x| {
@@ -1,50 +1,180 @@
 // This is the audio file reader that supports both WAV and MP3 files
 // Returns mono samples as a vector of i16 along with the sample rate.
 // Ensures consistent mono, 16-bit format for fingerprinting.
+//
+// `open_audio_blocks` is the streaming form: it decodes lazily and yields the mono signal in
+// blocks, so a file never has to fit in memory. `read_audio_file` collects those blocks. Both
+// apply the same format checks, the streaming form once its blocks run out.
 
+use crate::error::{NumeroError, Result};
 use crate::utils;
+use rodio::source::SamplesConverter;
 use rodio::{Decoder, Source};
 use std::fs::File;
-use std::io::{self, BufReader};
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
 
-pub fn read_audio_file(path: &str) -> Result<(Vec<i16>, u32), io::Error> {
-    // Open the file
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
+/// Mono samples per block used by `read_audio_file`
+const READ_BLOCK_SIZE: usize = 1 << 16;
+
+pub fn read_audio_file(path: impl AsRef<Path>) -> Result<(Vec<i16>, u32)> {
+    open_audio_blocks(path, READ_BLOCK_SIZE)?.read_all()
+}
+
+/// Opens an audio file for block-wise decoding.
+/// The sample rate is validated up front; the samples themselves are only decoded as the
+/// returned iterator is advanced.
+pub fn open_audio_blocks(path: impl AsRef<Path>, block_size: usize) -> Result<AudioBlocks> {
+    check_block_size(block_size)?;
+    decode_audio_blocks(BufReader::new(File::open(path)?), block_size)
+}
+
+/// `open_audio_blocks` for audio that is already open, e.g. behind a reader that observes the
+/// bytes as they are decoded
+pub fn decode_audio_blocks<R>(reader: R, block_size: usize) -> Result<AudioBlocks<R>>
+where
+    R: Read + Seek + Send + Sync + 'static,
+{
+    check_block_size(block_size)?;
 
     // Create decoder (supports both WAV and MP3)
-    let decoder = Decoder::new(reader).map_err(|e| {
-        io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!("Failed to decode audio: {}", e),
-        )
-    })?;
+    let decoder = Decoder::new(reader).map_err(|e| NumeroError::Decode(e.to_string()))?;
 
     // Get the sample rate and channels
     let sample_rate = decoder.sample_rate();
-    let channels = decoder.channels();
+    let channels = decoder.channels().max(1);
+    utils::validate_sample_rate(sample_rate)?;
+
+    Ok(AudioBlocks {
+        samples: decoder.convert_samples(),
+        sample_rate,
+        channels,
+        block_size,
+        samples_read: 0,
+        previous: None,
+        total_variation: 0.0,
+    })
+}
+
+fn check_block_size(block_size: usize) -> Result<()> {
+    if block_size == 0 {
+        return Err(NumeroError::InvalidConfig(
+            "block size must be positive".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Iterator over the mono signal of a decoded file, `block_size` samples at a time.
+/// Only the last block may be shorter; a trailing partial multi-channel frame is dropped.
+pub struct AudioBlocks<R = BufReader<File>>
+where
+    R: Read + Seek + Send + Sync + 'static,
+{
+    samples: SamplesConverter<Decoder<R>, i16>,
+    sample_rate: u32,
+    channels: u16,
+    block_size: usize,
+    // What `check_format` needs of the samples returned so far
+    samples_read: usize,
+    previous: Option<i16>,
+    total_variation: f32,
+}
+
+impl<R> AudioBlocks<R>
+where
+    R: Read + Seek + Send + Sync + 'static,
+{
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Channels in the file, before downmixing
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
 
-    // Convert to mono i16 samples
-    let samples: Vec<i16> = decoder.convert_samples().collect();
-    let num_frames = samples.len() / channels as usize;
+    /// Mono samples returned so far
+    pub fn samples_read(&self) -> usize {
+        self.samples_read
+    }
 
-    let mut mono_samples: Vec<i16> = Vec::with_capacity(num_frames);
+    /// Checks the samples returned so far the way `utils::validate_audio_format` checks a whole
+    /// signal. Called once the blocks are exhausted, it accepts or rejects a file exactly as
+    /// `read_audio_file` does.
+    pub fn check_format(&self) -> Result<()> {
+        if self.samples_read == 0 {
+            return Err(NumeroError::EmptyInput);
+        }
+        utils::check_sample_variation(self.total_variation, self.samples_read)
+    }
 
-    // Downmix stereo to mono by averaging the channels
-    for i in 0..num_frames {
+    /// Decodes the remaining audio at once and checks the whole file with `check_format`.
+    /// Returns the samples and the sample rate.
+    pub fn read_all(mut self) -> Result<(Vec<i16>, u32)> {
+        let mut mono_samples: Vec<i16> = Vec::new();
+        for block in &mut self {
+            mono_samples.extend_from_slice(&block);
+        }
+        self.check_format()?;
+
+        Ok((mono_samples, self.sample_rate))
+    }
+
+    // Downmix one multi-channel frame to mono by averaging the channels
+    fn next_mono_sample(&mut self) -> Option<i16> {
         let mut sum: i32 = 0;
-        for j in 0..channels as usize {
-            let sample = samples[i * channels as usize + j] as i32;
-            sum += sample;
+        for _ in 0..self.channels {
+            sum += self.samples.next()? as i32;
         }
-        let mono_sample = (sum / channels as i32) as i16;
-        mono_samples.push(mono_sample);
+        Some((sum / self.channels as i32) as i16)
     }
+}
 
-    // Validate the audio format
-    if let Err(e) = utils::validate_audio_format(&mono_samples, sample_rate) {
-        return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+impl<R> Iterator for AudioBlocks<R>
+where
+    R: Read + Seek + Send + Sync + 'static,
+{
+    type Item = Vec<i16>;
+
+    fn next(&mut self) -> Option<Vec<i16>> {
+        let mut block = Vec::with_capacity(self.block_size);
+        while block.len() < self.block_size {
+            match self.next_mono_sample() {
+                Some(sample) => block.push(sample),
+                None => break,
+            }
+        }
+
+        for &sample in &block {
+            if let Some(previous) = self.previous {
+                self.total_variation += utils::sample_variation(previous, sample);
+            }
+            self.previous = Some(sample);
+        }
+        self.samples_read += block.len();
+
+        if block.is_empty() {
+            None
+        } else {
+            Some(block)
+        }
     }
+}
 
-    Ok((mono_samples, sample_rate))
+impl<R> std::fmt::Debug for AudioBlocks<R>
+where
+    R: Read + Seek + Send + Sync + 'static,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AudioBlocks")
+            .field("sample_rate", &self.sample_rate)
+            .field("channels", &self.channels)
+            .field("block_size", &self.block_size)
+            .finish()
+    }
 }
@@ -0,0 +1,113 @@
+mod common;
+
+use common::notes;
+use numero::fingerprint::bands::{band_ranges, bark_to_hz, hz_to_bark, hz_to_mel, mel_to_hz};
+use numero::fingerprint::fingerprint::analyze;
+use numero::fingerprint::peaks::detect_peaks;
+use numero::index::store::{read_fingerprints, write_fingerprints};
+use numero::{finger_print, BandLayout, FingerprintConfig};
+
+fn config(band_layout: BandLayout) -> FingerprintConfig {
+    FingerprintConfig {
+        band_layout,
+        ..FingerprintConfig::default()
+    }
+}
+
+#[test]
+fn linear_layout_matches_equal_width_band_maxima() {
+    let config = config(BandLayout::Linear);
+    let analysis = analyze(&notes(44100, 0, 3.0), 44100, &config).unwrap();
+    let expected = detect_peaks(
+        &analysis.spectrogram,
+        config.num_bands,
+        config.threshold_multiplier,
+    );
+
+    let key = |p: &numero::Peak| (p.frame_index, p.freq_bin);
+    assert_eq!(
+        analysis.peaks.iter().map(key).collect::<Vec<_>>(),
+        expected.iter().map(key).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn perceptual_layouts_tile_the_spectrum_finest_at_the_bottom() {
+    for layout in [BandLayout::Log, BandLayout::Bark, BandLayout::Mel] {
+        for num_bands in [6, 24, 100] {
+            let config = FingerprintConfig {
+                num_bands,
+                ..config(layout.clone())
+            };
+            let bands = band_ranges(&config);
+
+            assert_eq!(bands.len(), num_bands);
+            assert_eq!(bands[0].start, 0);
+            assert_eq!(bands[num_bands - 1].end, 513);
+            assert!(bands.windows(2).all(|w| w[0].end == w[1].start));
+            assert!(bands.iter().all(|band| !band.is_empty()));
+            assert!(
+                bands[0].len() < bands[num_bands - 1].len(),
+                "{} with {} bands",
+                layout.name(),
+                num_bands
+            );
+        }
+    }
+
+    // With six log bands, 110 Hz and 440 Hz no longer share the lowest band
+    let bands = band_ranges(&config(BandLayout::Log));
+    let bin = |hz: f64| (hz * 1024.0 / 11025.0).round() as usize;
+    assert!(!bands
+        .iter()
+        .any(|band| band.contains(&bin(110.0)) && band.contains(&bin(440.0))));
+}
+
+#[test]
+fn scale_conversions_round_trip() {
+    for hz in [0.0, 100.0, 1000.0, 5512.5] {
+        assert!((mel_to_hz(hz_to_mel(hz)) - hz).abs() < 1e-6);
+        assert!((bark_to_hz(hz_to_bark(hz)) - hz).abs() < 1e-6);
+    }
+    assert!((hz_to_mel(1000.0) - 1000.0).abs() < 0.5);
+}
+
+#[test]
+fn custom_edges_select_bins_by_frequency() {
+    let config = config(BandLayout::Custom(vec![0.0, 300.0, 1000.0, 5512.5]));
+    config.validate().unwrap();
+
+    // Bins are 11025 / 1024 ≈ 10.77 Hz apart
+    let bands = band_ranges(&config);
+    assert_eq!(bands, vec![0..28, 28..93, 93..513]);
+
+    for edges in [
+        vec![0.0],
+        vec![500.0, 200.0],
+        vec![0.0, 6000.0],
+        vec![100.0, 101.0],
+    ] {
+        assert!(config_with(edges).validate().is_err());
+    }
+}
+
+fn config_with(edges: Vec<f64>) -> FingerprintConfig {
+    config(BandLayout::Custom(edges))
+}
+
+#[test]
+fn band_layout_is_stored_with_the_config() {
+    let set = finger_print(
+        &notes(44100, 0, 2.0),
+        44100,
+        &config_with(vec![40.0, 200.0, 800.0, 4000.0]),
+    )
+    .unwrap();
+    let mut bytes = Vec::new();
+    write_fingerprints(&mut bytes, &set).unwrap();
+    assert_eq!(
+        read_fingerprints(&mut bytes.as_slice(), &set.config).unwrap(),
+        set
+    );
+    assert!(read_fingerprints(&mut bytes.as_slice(), &config(BandLayout::Mel)).is_err());
+}
@@ -0,0 +1,162 @@
+mod common;
+
+use common::{scratch_dir, track, write_wav, SAMPLE_RATE};
+use numero::{finger_print, BatchIndexer, FingerprintConfig, FingerprintIndex, NumeroError};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// A library of four tracks, one of them in a subdirectory, plus a broken file and a non-audio
+/// file. Returns the root and the tracks in path order.
+fn library(name: &str) -> (PathBuf, Vec<(PathBuf, Vec<i16>)>) {
+    let root = scratch_dir("batch", name);
+    fs::create_dir(root.join("live")).unwrap();
+
+    let mut tracks = Vec::new();
+    for (seed, file) in ["a.wav", "b.WAV", "c.wav", "live/d.wav"].iter().enumerate() {
+        let path = root.join(file);
+        let samples = track(seed, 5.0);
+        write_wav(&path, &samples, SAMPLE_RATE, 1);
+        tracks.push((path, samples));
+    }
+    fs::write(root.join("broken.wav"), b"not audio").unwrap();
+    fs::write(root.join("notes.txt"), b"ignored").unwrap();
+
+    tracks.sort_by(|a, b| a.0.cmp(&b.0));
+    (root, tracks)
+}
+
+#[test]
+fn indexes_every_file_below_a_directory_and_skips_broken_ones() {
+    let (root, tracks) = library("walk");
+    let mut index = FingerprintIndex::new(FingerprintConfig::music());
+    let progress = Mutex::new(Vec::new());
+
+    let report = BatchIndexer::new()
+        .with_threads(3)
+        .with_files_in_flight(2)
+        .index_dir(&root, &mut index, |update| {
+            assert_eq!(update.total, 5);
+            progress
+                .lock()
+                .unwrap()
+                .push((update.completed, update.path.to_path_buf()));
+        })
+        .unwrap();
+
+    let added: Vec<&PathBuf> = report.added.iter().map(|file| &file.path).collect();
+    let expected: Vec<&PathBuf> = tracks.iter().map(|(path, _)| path).collect();
+    assert_eq!(added, expected);
+    assert_eq!(index.track_count(), 4);
+    for (id, file) in report.added.iter().enumerate() {
+        assert_eq!(file.track_id, id as u32);
+    }
+
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(report.skipped[0].path, root.join("broken.wav"));
+
+    // One update per file, counting up to the total
+    let mut progress = progress.into_inner().unwrap();
+    progress.sort();
+    let completed: Vec<usize> = progress.iter().map(|(completed, _)| *completed).collect();
+    assert_eq!(completed, vec![1, 2, 3, 4, 5]);
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn links_to_directories_are_not_followed() {
+    let (root, tracks) = library("links");
+    std::os::unix::fs::symlink(&root, root.join("live/again")).unwrap();
+    std::os::unix::fs::symlink(root.join("live"), root.join("live/again2")).unwrap();
+
+    let mut index = FingerprintIndex::new(FingerprintConfig::music());
+    let report = BatchIndexer::new()
+        .index_dir(&root, &mut index, |_| {})
+        .unwrap();
+    assert_eq!(report.added.len(), tracks.len());
+    assert_eq!(index.track_count(), tracks.len());
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn file_names_need_not_be_utf8() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let root = scratch_dir("batch", "bytes");
+    let path = root.join(std::ffi::OsStr::from_bytes(b"caf\xe9.wav"));
+    write_wav(&path, &track(0, 3.0), SAMPLE_RATE, 1);
+
+    let mut index = FingerprintIndex::new(FingerprintConfig::music());
+    let report = BatchIndexer::new()
+        .index_dir(&root, &mut index, |_| {})
+        .unwrap();
+    assert!(report.skipped.is_empty());
+    assert_eq!(report.added.len(), 1);
+    assert_eq!(report.added[0].path, path);
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn parallel_index_matches_a_sequential_one() {
+    let (root, tracks) = library("sequential");
+    let config = FingerprintConfig::music();
+
+    let mut sequential = FingerprintIndex::new(config.clone());
+    for (path, samples) in &tracks {
+        let set = finger_print(samples, SAMPLE_RATE, &config).unwrap();
+        sequential
+            .add_track(&path.display().to_string(), &set)
+            .unwrap();
+    }
+    let mut parallel = FingerprintIndex::new(config.clone());
+    BatchIndexer::new()
+        .with_files_in_flight(3)
+        .index_dir(&root, &mut parallel, |_| {})
+        .unwrap();
+
+    assert_eq!(parallel.hash_count(), sequential.hash_count());
+    for (a, b) in parallel.tracks().zip(sequential.tracks()) {
+        assert_eq!((a.id, &a.name), (b.id, &b.name));
+        assert_eq!(a.fingerprint_count, b.fingerprint_count);
+    }
+
+    let start = 2 * SAMPLE_RATE as usize;
+    let clip = finger_print(
+        &tracks[2].1[start..start + 3 * SAMPLE_RATE as usize],
+        SAMPLE_RATE,
+        &config,
+    )
+    .unwrap();
+    assert_eq!(
+        parallel.query(&clip).unwrap(),
+        sequential.query(&clip).unwrap()
+    );
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn missing_directory_and_bad_settings_are_errors() {
+    let mut index = FingerprintIndex::new(FingerprintConfig::music());
+    let missing = std::env::temp_dir().join("numero-batch-does-not-exist");
+    assert!(BatchIndexer::new()
+        .index_dir(&missing, &mut index, |_| {})
+        .is_err());
+
+    let dir = std::env::temp_dir();
+    for indexer in [
+        BatchIndexer::new().with_files_in_flight(0),
+        BatchIndexer::new().with_block_size(0),
+    ] {
+        assert!(matches!(
+            indexer.index_files(&[], &mut index, |_| {}),
+            Err(NumeroError::InvalidConfig(_))
+        ));
+        assert!(indexer.index_dir(&dir, &mut index, |_| {}).is_err());
+    }
+}
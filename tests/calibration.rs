@@ -0,0 +1,65 @@
+mod common;
+
+use common::{track, SAMPLE_RATE};
+use numero::{finger_print, match_fingerprints, Calibration, FingerprintConfig, FingerprintIndex};
+
+#[test]
+fn threshold_bounds_the_negative_acceptance_rate() {
+    let scores: Vec<f64> = (1..=100).map(|s| s as f64).collect();
+
+    let calibration = Calibration::from_negative_scores(&scores, 0.05).unwrap();
+    assert_eq!(calibration.threshold, 95.0);
+    assert_eq!(calibration.false_positive_rate, 0.05);
+    assert_eq!(calibration.negatives, 100);
+    assert!(calibration.accepts(95.5));
+    assert!(!calibration.accepts(95.0));
+
+    let strict = Calibration::from_negative_scores(&scores, 0.0).unwrap();
+    assert_eq!(strict.threshold, 100.0);
+    assert_eq!(strict.false_positive_rate, 0.0);
+}
+
+#[test]
+fn calibration_rejects_unusable_input() {
+    assert!(Calibration::from_negative_scores(&[], 0.01).is_err());
+    assert!(Calibration::from_negative_scores(&[1.0, 2.0], 1.0).is_err());
+    assert!(Calibration::from_negative_scores(&[1.0, 2.0], -0.1).is_err());
+    assert!(Calibration::from_negative_scores(&[1.0, f64::NAN], 0.1).is_err());
+}
+
+#[test]
+fn true_matches_clear_a_threshold_calibrated_on_negatives() {
+    let config = FingerprintConfig::music();
+    let mut index = FingerprintIndex::new(config.clone());
+    let songs: Vec<Vec<i16>> = (0..3).map(|seed| track(seed, 10.0)).collect();
+    for (i, song) in songs.iter().enumerate() {
+        let set = finger_print(song, SAMPLE_RATE, &config).unwrap();
+        index.add_track(&format!("song{}", i), &set).unwrap();
+    }
+
+    let clip = |samples: &[i16]| {
+        let start = 3 * SAMPLE_RATE as usize;
+        finger_print(
+            &samples[start..start + 3 * SAMPLE_RATE as usize],
+            SAMPLE_RATE,
+            &config,
+        )
+        .unwrap()
+    };
+    let negatives: Vec<_> = (3..7).map(|seed| clip(&track(seed, 7.0))).collect();
+    let calibration = index.calibrate(&negatives, 0.0).unwrap();
+    assert_eq!(calibration.negatives, 4);
+
+    let positive = clip(&songs[2]);
+    let best = &index.query(&positive).unwrap()[0];
+    assert_eq!(best.name, "song2");
+    assert!(calibration.accepts(best.significance));
+
+    // The pairwise score follows the same statistic
+    let song = finger_print(&songs[2], SAMPLE_RATE, &config).unwrap();
+    let unrelated = finger_print(&songs[0], SAMPLE_RATE, &config).unwrap();
+    assert!(
+        match_fingerprints(&song.fingerprints, &positive.fingerprints)
+            > match_fingerprints(&unrelated.fingerprints, &positive.fingerprints)
+    );
+}
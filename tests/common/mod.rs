@@ -0,0 +1,118 @@
+// Fixtures shared by the integration tests. Each test binary uses a different subset.
+#![allow(dead_code)]
+
+use std::f64::consts::PI;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const SAMPLE_RATE: u32 = 22050;
+
+/// Two-tone notes, five per second, whose pitches depend on `seed`. Defined in continuous time,
+/// so renditions at different rates or speeds hold the same music.
+pub fn melody(seed: usize, t: f64) -> f64 {
+    let note = (t * 5.0) as usize;
+    let f1 = 250.0 + 60.0 * (note * (3 + 2 * seed) % 17) as f64;
+    let f2 = 1100.0 + 210.0 * (note * (2 + seed) % 13 + seed) as f64;
+    0.4 * (2.0 * PI * f1 * t).sin() + 0.3 * (2.0 * PI * f2 * t).sin()
+}
+
+/// Decaying three-partial notes, four per second, with pitches drawn from a generator seeded by
+/// `seed` over a continuous three-octave range. Continuous in time like `melody`.
+pub fn chimes(seed: usize, t: f64) -> f64 {
+    let note = (t * 4.0) as usize;
+    let mut state = 0x2545_f491_u32.wrapping_add(seed as u32);
+    for _ in 0..=note {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+    }
+    let f = 220.0 * 2f64.powf((state >> 16) as f64 / 65536.0 * 3.0);
+    let age = (t * 4.0).fract() / 4.0;
+    let envelope = (-age * 6.0).exp();
+    (1..=3)
+        .map(|h| (2.0 * PI * f * h as f64 * t).sin() / h as f64)
+        .sum::<f64>()
+        * envelope
+        * 0.5
+}
+
+/// `seconds` of `melody` rendered as 16-bit samples at `sample_rate`
+pub fn notes(sample_rate: u32, seed: usize, seconds: f64) -> Vec<i16> {
+    (0..(sample_rate as f64 * seconds) as usize)
+        .map(|i| (melody(seed, i as f64 / sample_rate as f64) * 20000.0) as i16)
+        .collect()
+}
+
+/// `notes` at `SAMPLE_RATE`
+pub fn track(seed: usize, seconds: f64) -> Vec<i16> {
+    notes(SAMPLE_RATE, seed, seconds)
+}
+
+/// White noise from a fixed generator, uniform within `amplitude` of zero
+pub fn noise(len: usize, amplitude: f64) -> Vec<i16> {
+    let mut state = 12345u32;
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            ((state as f64 / u32::MAX as f64 * 2.0 - 1.0) * amplitude) as i16
+        })
+        .collect()
+}
+
+/// `samples` over `noise` of the given amplitude
+pub fn with_noise(samples: Vec<i16>, amplitude: f64) -> Vec<i16> {
+    let noise = noise(samples.len(), amplitude);
+    samples
+        .into_iter()
+        .zip(noise)
+        .map(|(sample, noise)| sample.saturating_add(noise))
+        .collect()
+}
+
+/// `samples` with a full-scale negative sample in the middle, which pins peak normalization
+/// to the i16 full scale
+pub fn with_spike(mut samples: Vec<i16>) -> Vec<i16> {
+    let middle = samples.len() / 2;
+    samples[middle] = i16::MIN;
+    samples
+}
+
+/// `samples` with the lowest bit cleared, so halving them is exact
+pub fn even(samples: Vec<i16>) -> Vec<i16> {
+    samples.into_iter().map(|sample| sample & !1).collect()
+}
+
+/// Interleaves two channels of equal length into stereo frames
+pub fn interleave(left: &[i16], right: &[i16]) -> Vec<i16> {
+    left.iter().zip(right).flat_map(|(&l, &r)| [l, r]).collect()
+}
+
+/// Writes interleaved 16-bit PCM as a WAV file
+pub fn write_wav(path: &Path, samples: &[i16], sample_rate: u32, channels: u16) {
+    let data_len = (samples.len() * 2) as u32;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * channels as u32 * 2).to_le_bytes());
+    bytes.extend_from_slice(&(channels * 2).to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+    fs::write(path, bytes).unwrap();
+}
+
+/// An empty directory for one test, named after the test binary and `name`, with its path
+/// resolved so it compares equal to the paths the indexer records
+pub fn scratch_dir(binary: &str, name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("numero-{}-{}-{}", binary, std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::canonicalize(dir).unwrap()
+}
@@ -0,0 +1,155 @@
+use numero::{
+    finger_print, FingerprintConfig, NumeroError, Pairing, PeakPicker, StreamingFingerprinter,
+};
+
+fn rejected(config: FingerprintConfig) -> bool {
+    matches!(config.validate(), Err(NumeroError::InvalidConfig(_)))
+}
+
+#[test]
+fn presets_are_valid() {
+    for config in [
+        FingerprintConfig::music(),
+        FingerprintConfig::speech(),
+        FingerprintConfig::broadcast(),
+    ] {
+        config.validate().unwrap();
+    }
+}
+
+#[test]
+fn target_rate_must_be_positive() {
+    assert!(rejected(FingerprintConfig {
+        target_sample_rate: 0,
+        ..FingerprintConfig::music()
+    }));
+}
+
+#[test]
+fn filter_taps_must_be_odd_and_at_least_three() {
+    let with_taps = |filter_taps| FingerprintConfig {
+        filter_taps,
+        ..FingerprintConfig::music()
+    };
+    for filter_taps in [0, 1, 2, 4, 100] {
+        assert!(rejected(with_taps(filter_taps)), "{}", filter_taps);
+    }
+    assert!(!rejected(with_taps(3)));
+}
+
+#[test]
+fn hop_must_fit_in_a_frame() {
+    let music = FingerprintConfig::music();
+    for hop_size in [0, music.frame_size + 1] {
+        assert!(rejected(FingerprintConfig {
+            hop_size,
+            ..music.clone()
+        }));
+    }
+    assert!(!rejected(FingerprintConfig {
+        hop_size: music.frame_size,
+        ..music
+    }));
+}
+
+#[test]
+fn frames_must_be_a_power_of_two() {
+    for frame_size in [0, 1, 1000, 1023] {
+        assert!(rejected(FingerprintConfig {
+            frame_size,
+            hop_size: 1,
+            ..FingerprintConfig::music()
+        }));
+    }
+}
+
+#[test]
+fn bands_must_fit_in_the_spectrum() {
+    let music = FingerprintConfig::music();
+    let num_bins = music.frame_size / 2 + 1;
+    let with_bands = |num_bands| FingerprintConfig {
+        num_bands,
+        ..FingerprintConfig::music()
+    };
+    assert!(rejected(with_bands(0)));
+    assert!(rejected(with_bands(num_bins + 1)));
+    assert!(!rejected(with_bands(num_bins)));
+}
+
+#[test]
+fn target_zone_must_not_be_empty() {
+    assert!(rejected(FingerprintConfig {
+        target_zone_frames: 0,
+        ..FingerprintConfig::music()
+    }));
+}
+
+#[test]
+fn threshold_must_be_a_non_negative_number() {
+    let with_threshold = |threshold_multiplier| FingerprintConfig {
+        threshold_multiplier,
+        ..FingerprintConfig::music()
+    };
+    for threshold in [f64::NAN, f64::INFINITY, -0.1] {
+        assert!(rejected(with_threshold(threshold)), "{}", threshold);
+    }
+    assert!(!rejected(with_threshold(0.0)));
+}
+
+#[test]
+fn constellation_needs_a_neighbourhood_and_a_peak_rate() {
+    let num_bins = FingerprintConfig::music().frame_size / 2 + 1;
+    let with_picker =
+        |neighborhood_frames, neighborhood_bins, peaks_per_second| FingerprintConfig {
+            peak_picker: PeakPicker::Constellation {
+                neighborhood_frames,
+                neighborhood_bins,
+                peaks_per_second,
+            },
+            ..FingerprintConfig::music()
+        };
+    assert!(!rejected(with_picker(3, 8, 30.0)));
+    assert!(!rejected(with_picker(1, num_bins - 1, 30.0)));
+    for (frames, bins) in [(0, 8), (3, 0), (3, num_bins)] {
+        assert!(
+            rejected(with_picker(frames, bins, 30.0)),
+            "{} {}",
+            frames,
+            bins
+        );
+    }
+    for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        assert!(rejected(with_picker(3, 8, rate)), "{}", rate);
+    }
+}
+
+#[test]
+fn fan_out_must_be_at_least_one() {
+    let with_fan_out = |max_fan_out| FingerprintConfig {
+        pairing: Pairing {
+            max_fan_out,
+            ..Pairing::default()
+        },
+        ..FingerprintConfig::music()
+    };
+    assert!(rejected(with_fan_out(Some(0))));
+    assert!(!rejected(with_fan_out(Some(1))));
+    assert!(!rejected(with_fan_out(None)));
+}
+
+#[test]
+fn input_must_not_be_below_the_target_rate() {
+    let config = FingerprintConfig::music();
+    let samples = vec![0i16; 8000];
+    let below = config.target_sample_rate - 1;
+
+    assert!(matches!(
+        finger_print(&samples, below, &config),
+        Err(NumeroError::UnsupportedSampleRate { sample_rate, .. }) if sample_rate == below
+    ));
+    assert!(matches!(
+        StreamingFingerprinter::new(below, &config),
+        Err(NumeroError::UnsupportedSampleRate { .. })
+    ));
+    assert!(StreamingFingerprinter::new(config.target_sample_rate, &config).is_ok());
+}
@@ -0,0 +1,96 @@
+use numero::dsp::fft::{compute_fft, SpectrumAnalyzer, SpectrumScale};
+use numero::fingerprint::spectogram::compute_spectrogram;
+use numero::fingerprint::utils::{frame_signal, hamming_window};
+use std::f64::consts::PI;
+
+/// Magnitudes of the one-sided DFT, computed by definition
+fn dft_magnitude(frame: &[f64]) -> Vec<f64> {
+    let n = frame.len();
+    (0..=n / 2)
+        .map(|k| {
+            let (re, im) = frame
+                .iter()
+                .enumerate()
+                .fold((0.0, 0.0), |(re, im), (t, &x)| {
+                    let angle = -2.0 * PI * (k * t) as f64 / n as f64;
+                    (re + x * angle.cos(), im + x * angle.sin())
+                });
+            (re * re + im * im).sqrt()
+        })
+        .collect()
+}
+
+fn test_signal(len: usize) -> Vec<f64> {
+    (0..len)
+        .map(|i| {
+            let t = i as f64;
+            (0.3 * t).sin() + 0.5 * (1.7 * t + 0.4).cos() + 0.1 * ((i * 7919) % 13) as f64
+        })
+        .collect()
+}
+
+fn assert_close(actual: &[f64], expected: &[f64], tolerance: f64) {
+    assert_eq!(actual.len(), expected.len());
+    for (k, (a, e)) in actual.iter().zip(expected).enumerate() {
+        assert!((a - e).abs() < tolerance, "bin {}: {} != {}", k, a, e);
+    }
+}
+
+#[test]
+fn real_fft_matches_dft_for_even_and_odd_sizes() {
+    for size in [2, 8, 64, 100, 1024, 7, 255] {
+        let frame = test_signal(size);
+        let analyzer = SpectrumAnalyzer::new(size, SpectrumScale::Magnitude);
+        assert_close(&analyzer.process(&frame), &dft_magnitude(&frame), 1e-8);
+    }
+}
+
+#[test]
+fn scales_are_derived_from_the_same_spectrum() {
+    let frame = test_signal(512);
+    let magnitude = SpectrumAnalyzer::new(512, SpectrumScale::Magnitude).process(&frame);
+    let power = SpectrumAnalyzer::new(512, SpectrumScale::Power).process(&frame);
+    let log_power = SpectrumAnalyzer::new(512, SpectrumScale::LogPower).process(&frame);
+
+    let expected_power: Vec<f64> = magnitude.iter().map(|m| m * m).collect();
+    assert_close(&power, &expected_power, 1e-6);
+    let expected_log: Vec<f64> = expected_power.iter().map(|p| 10.0 * p.log10()).collect();
+    assert_close(&log_power, &expected_log, 1e-6);
+
+    let silence = SpectrumAnalyzer::new(512, SpectrumScale::LogPower).process(&[0.0; 512]);
+    assert!(silence.iter().all(|db| db.is_finite()));
+}
+
+#[test]
+fn spectrogram_matches_framed_computation() {
+    let signal = test_signal(10_000);
+    let window = hamming_window(1024);
+
+    let analyzer =
+        SpectrumAnalyzer::new(1024, SpectrumScale::Magnitude).with_window(window.clone());
+    let spectrogram = analyzer.spectrogram(&signal, 512);
+
+    let frames = frame_signal(&signal, 1024, 512);
+    assert_eq!(spectrogram.len(), frames.len());
+    for (spectrum, frame) in spectrogram.iter().zip(&frames) {
+        let windowed: Vec<f64> = frame.iter().zip(&window).map(|(x, w)| x * w).collect();
+        assert_close(spectrum, &dft_magnitude(&windowed), 1e-8);
+    }
+
+    // The frame-list API agrees, up to its f32 inputs
+    let legacy = compute_spectrogram(
+        frames
+            .iter()
+            .map(|f| f.iter().map(|&x| x as f32).collect())
+            .collect(),
+        window.iter().map(|&w| w as f32).collect(),
+    );
+    for (a, b) in legacy.iter().zip(&spectrogram) {
+        assert_close(a, b, 1e-3);
+    }
+    assert_close(
+        &compute_fft(frames[0].iter().map(|&x| x as f32).collect()),
+        &dft_magnitude(&frames[0]),
+        1e-3,
+    );
+}
@@ -0,0 +1,127 @@
+use numero::fingerprint::fingerprint::analyze;
+use numero::{finger_print, FingerprintConfig, HashCodec};
+use std::collections::HashSet;
+use std::f64::consts::PI;
+
+#[test]
+fn fitted_layout_holds_every_bin_and_frame_difference() {
+    let codec = HashCodec::fitted(513, 20, 1);
+    assert_eq!(
+        (codec.freq_bits, codec.dt_bits, codec.hash_bits()),
+        (10, 5, 25)
+    );
+    assert_eq!(FingerprintConfig::music().hash_codec, codec);
+
+    let mut seen = HashSet::new();
+    for f1 in (0..511).step_by(8).chain([511, 512]) {
+        for f2 in [0, 255, 511, 512] {
+            for dt in 0..=20 {
+                let hash = codec.encode(f1, f2, dt);
+                assert_eq!(codec.decode(hash), (f1, f2, dt));
+                assert!(seen.insert(hash));
+            }
+        }
+    }
+
+    // The legacy layout merges the top bins
+    let legacy = HashCodec::LEGACY;
+    assert_eq!(legacy.encode(511, 0, 3), legacy.encode(512, 0, 3));
+    assert_eq!(legacy.encode(300, 7, 12), (300 << 23) | (7 << 14) | 12);
+}
+
+#[test]
+fn quantization_merges_neighbouring_bins() {
+    let codec = HashCodec::fitted(513, 20, 4);
+    assert_eq!(codec.freq_bits, 8);
+    assert_eq!(codec.encode(100, 200, 5), codec.encode(103, 201, 5));
+    assert_ne!(codec.encode(100, 200, 5), codec.encode(104, 200, 5));
+    assert_eq!(codec.decode(codec.encode(103, 201, 5)), (100, 200, 5));
+    assert!(codec.max_freq_bin() >= 512);
+}
+
+#[test]
+fn layouts_that_do_not_fit_are_rejected() {
+    let too_wide = HashCodec {
+        freq_bits: 12,
+        dt_bits: 10,
+        freq_step: 1,
+    };
+    assert!(too_wide.validate(20).is_err());
+    assert!(HashCodec::fitted(513, 20, 1).validate(40).is_err());
+
+    let config = FingerprintConfig {
+        target_zone_frames: 40,
+        ..FingerprintConfig::music()
+    };
+    assert!(config.validate().is_err());
+
+    // Fields too wide to add up are an error, not an overflow
+    let huge = HashCodec {
+        freq_bits: u32::MAX,
+        dt_bits: u32::MAX,
+        freq_step: 1,
+    };
+    assert_eq!(huge.hash_bits(), u32::MAX);
+    assert!(huge.validate(20).is_err());
+}
+
+#[test]
+fn codec_must_hold_every_bin_of_the_frame() {
+    // A larger frame with the preset's codec would merge its top bins
+    let music = FingerprintConfig::music();
+    let larger = FingerprintConfig {
+        frame_size: 2048,
+        hop_size: 1024,
+        ..music.clone()
+    };
+    assert_eq!(
+        larger.hash_codec.encode(1023, 5, 3),
+        larger.hash_codec.encode(1024, 5, 3)
+    );
+    assert!(larger.validate().is_err());
+
+    let refitted = FingerprintConfig {
+        hash_codec: HashCodec::fitted(2048 / 2 + 1, music.target_zone_frames, 1),
+        ..larger
+    };
+    refitted.validate().unwrap();
+    assert_ne!(
+        refitted.hash_codec.encode(1023, 5, 3),
+        refitted.hash_codec.encode(1024, 5, 3)
+    );
+
+    // Coarser steps still have to reach the top bin
+    let quantized = FingerprintConfig {
+        hash_codec: HashCodec {
+            freq_bits: 7,
+            dt_bits: 5,
+            freq_step: 4,
+        },
+        ..music
+    };
+    assert!(quantized.validate().is_err());
+}
+
+#[test]
+fn fingerprints_decode_to_their_peaks() {
+    let samples: Vec<i16> = (0..44100 * 3)
+        .map(|i| {
+            let t = i as f64 / 44100.0;
+            let f = 300.0 + 500.0 * ((t * 3.0) as usize % 9) as f64;
+            ((2.0 * PI * f * t).sin() * 15000.0 + (2.0 * PI * 5400.0 * t).sin() * 8000.0) as i16
+        })
+        .collect();
+    let config = FingerprintConfig::default();
+    let set = finger_print(&samples, 44100, &config).unwrap();
+    let peaks = analyze(&samples, 44100, &config).unwrap().peaks;
+
+    let peak_bins: HashSet<(usize, u32)> = peaks
+        .iter()
+        .map(|p| (p.frame_index, p.freq_bin as u32))
+        .collect();
+    for fp in &set.fingerprints {
+        let (f1, _, dt) = config.hash_codec.decode(fp.hash);
+        assert!(peak_bins.contains(&(fp.anchor_frame, f1)));
+        assert!(dt as usize <= config.target_zone_frames);
+    }
+}
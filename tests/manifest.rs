@@ -0,0 +1,223 @@
+mod common;
+
+use common::{scratch_dir, track, write_wav, SAMPLE_RATE};
+use numero::index::manifest::file_checksum;
+use numero::{finger_print, BatchIndexer, FingerprintConfig, FingerprintIndex};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Moves a file's modification time without touching its content
+fn touch(path: &Path) {
+    let later = SystemTime::now() + Duration::from_secs(60);
+    File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(later)
+        .unwrap();
+}
+
+fn library(name: &str, seeds: &[usize]) -> PathBuf {
+    let root = scratch_dir("manifest", name);
+    for &seed in seeds {
+        write_wav(
+            &root.join(format!("song{}.wav", seed)),
+            &track(seed, 4.0),
+            SAMPLE_RATE,
+            1,
+        );
+    }
+    root
+}
+
+fn clip_of(seed: usize, config: &FingerprintConfig) -> numero::FingerprintSet {
+    let song = track(seed, 4.0);
+    let start = SAMPLE_RATE as usize;
+    finger_print(
+        &song[start..start + 2 * SAMPLE_RATE as usize],
+        SAMPLE_RATE,
+        config,
+    )
+    .unwrap()
+}
+
+#[test]
+fn rerun_only_processes_new_and_changed_files_and_drops_deleted_ones() {
+    let root = library("rerun", &[0, 1, 2]);
+    let config = FingerprintConfig::music();
+    let mut index = FingerprintIndex::new(config.clone());
+    let indexer = BatchIndexer::new();
+
+    let first = indexer.index_dir(&root, &mut index, |_| {}).unwrap();
+    assert_eq!(first.added.len(), 3);
+    // Checksums taken while decoding cover every byte of the file
+    for entry in index.manifest().entries() {
+        assert_eq!(entry.checksum, file_checksum(&entry.path).unwrap());
+    }
+    let entry = index.manifest().get(&root.join("song1.wav")).unwrap();
+    assert_eq!(entry.track_id, 1);
+    assert!((entry.duration_secs - 4.0).abs() < 1e-9);
+
+    // Nothing changed: nothing is decoded
+    let second = indexer
+        .index_dir(&root, &mut index, |_| panic!("no file needs processing"))
+        .unwrap();
+    assert!(second.added.is_empty() && second.removed.is_empty());
+    assert_eq!(second.unchanged.len(), 3);
+
+    // song0 only touched, song1 rewritten with other audio, song2 deleted, song3 new
+    touch(&root.join("song0.wav"));
+    write_wav(&root.join("song1.wav"), &track(5, 4.0), SAMPLE_RATE, 1);
+    fs::remove_file(root.join("song2.wav")).unwrap();
+    write_wav(&root.join("song3.wav"), &track(3, 4.0), SAMPLE_RATE, 1);
+
+    let third = indexer.index_dir(&root, &mut index, |_| {}).unwrap();
+    assert_eq!(third.unchanged, vec![root.join("song0.wav")]);
+    let added: Vec<(&PathBuf, Option<u32>)> = third
+        .added
+        .iter()
+        .map(|file| (&file.path, file.replaced))
+        .collect();
+    assert_eq!(
+        added,
+        vec![
+            (&root.join("song1.wav"), Some(1)),
+            (&root.join("song3.wav"), None)
+        ]
+    );
+    assert_eq!(third.removed.len(), 1);
+    assert_eq!(third.removed[0].path, root.join("song2.wav"));
+    assert_eq!(third.removed[0].track_id, 2);
+
+    assert_eq!(index.track_count(), 3);
+    assert_eq!(index.manifest().len(), 3);
+    assert!(index.track(1).is_none() && index.track(2).is_none());
+
+    // The rewritten file is found by its new content only
+    let best = &index.query(&clip_of(5, &config)).unwrap()[0];
+    assert_eq!(best.name, root.join("song1.wav").display().to_string());
+    assert!(index
+        .query(&clip_of(2, &config))
+        .unwrap()
+        .iter()
+        .all(|m| m.significance < 5.0));
+
+    // The touched file's new timestamp was recorded
+    let touched = indexer
+        .index_dir(&root, &mut index, |_| panic!("no file needs processing"))
+        .unwrap();
+    assert_eq!(touched.unchanged.len(), 3);
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn differently_spelled_paths_name_the_same_files() {
+    let root = library("spelling", &[0, 1]);
+    let mut index = FingerprintIndex::new(FingerprintConfig::music());
+    let indexer = BatchIndexer::new();
+    indexer.index_dir(&root, &mut index, |_| {}).unwrap();
+
+    // The same directory through `.`, `..` and relative to the working directory
+    let cwd = std::env::current_dir().unwrap();
+    let relative = Path::new(&"../".repeat(cwd.components().count() - 1))
+        .join(root.strip_prefix("/").unwrap());
+    let spellings = [
+        root.join("."),
+        root.join("..").join(root.file_name().unwrap()),
+        relative,
+    ];
+    for dir in &spellings {
+        let report = indexer
+            .index_dir(dir, &mut index, |_| panic!("no file needs processing"))
+            .unwrap();
+        assert_eq!(report.unchanged.len(), 2);
+        assert!(report.added.is_empty() && report.removed.is_empty());
+    }
+    assert_eq!(index.track_count(), 2);
+
+    // Given file by file, duplicates collapse as well
+    let files = vec![root.join("song0.wav"), root.join(".").join("song0.wav")];
+    let report = indexer.index_files(&files, &mut index, |_| {}).unwrap();
+    assert_eq!(report.unchanged, vec![root.join("song0.wav")]);
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn manifest_survives_a_save_and_load() {
+    let root = library("store", &[0, 1]);
+    let config = FingerprintConfig::music();
+    let mut index = FingerprintIndex::new(config.clone());
+    BatchIndexer::new()
+        .index_dir(&root, &mut index, |_| {})
+        .unwrap();
+
+    let db = root.join("index.numr");
+    index.save(&db).unwrap();
+    let mut loaded = FingerprintIndex::load(&db, &config).unwrap();
+    assert_eq!(loaded.manifest(), index.manifest());
+
+    let report = BatchIndexer::new()
+        .index_dir(&root, &mut loaded, |_| {})
+        .unwrap();
+    assert!(report.added.is_empty());
+    assert_eq!(report.unchanged.len(), 2);
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_paths_survive_a_save_and_load() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let root = library("bytes", &[0]);
+    let odd = root.join(std::ffi::OsStr::from_bytes(b"caf\xe9.wav"));
+    write_wav(&odd, &track(1, 4.0), SAMPLE_RATE, 1);
+    let config = FingerprintConfig::music();
+    let mut index = FingerprintIndex::new(config.clone());
+    BatchIndexer::new()
+        .index_dir(&root, &mut index, |_| {})
+        .unwrap();
+
+    let db = root.join("index.numr");
+    index.save(&db).unwrap();
+    let mut loaded = FingerprintIndex::load(&db, &config).unwrap();
+    assert!(loaded.manifest().get(&odd).is_some());
+
+    let report = BatchIndexer::new()
+        .index_dir(&root, &mut loaded, |_| panic!("no file needs processing"))
+        .unwrap();
+    assert!(report.removed.is_empty());
+    assert_eq!(report.unchanged.len(), 2);
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn tracks_added_without_a_manifest_are_replaced_by_path() {
+    let root = library("legacy", &[0, 1]);
+    let config = FingerprintConfig::music();
+    let mut index = FingerprintIndex::new(config.clone());
+    let path = root.join("song0.wav");
+    let set = finger_print(&track(0, 4.0), SAMPLE_RATE, &config).unwrap();
+    index.add_track(&path.display().to_string(), &set).unwrap();
+    index.add_track("elsewhere", &set).unwrap();
+
+    let report = BatchIndexer::new()
+        .index_dir(&root, &mut index, |_| {})
+        .unwrap();
+    assert_eq!(report.added[0].path, path);
+    assert_eq!(report.added[0].replaced, Some(0));
+    assert_eq!(report.added[1].replaced, None);
+
+    // The unrelated track has no source file below the directory and stays
+    let names: Vec<&str> = index.tracks().map(|info| info.name.as_str()).collect();
+    assert_eq!(names.len(), 3);
+    assert!(names.contains(&"elsewhere"));
+    assert_eq!(index.manifest().len(), 2);
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use common::{even, notes};
+use numero::fingerprint::fingerprint::analyze;
+use numero::index::store::{read_fingerprints, write_fingerprints, MAGIC};
+use numero::{finger_print, FingerprintConfig, FingerprintSet, Normalization, PeakPicker};
+
+fn config(normalization: Normalization) -> FingerprintConfig {
+    FingerprintConfig {
+        normalization,
+        ..FingerprintConfig::default()
+    }
+}
+
+#[test]
+fn a_quieter_clip_matches_the_same_stretch_of_the_full_track() {
+    let config = |normalization| FingerprintConfig {
+        normalization,
+        peak_picker: PeakPicker::constellation(),
+        ..FingerprintConfig::music()
+    };
+    let rate = FingerprintConfig::music().target_sample_rate;
+    let hop = FingerprintConfig::music().hop_size;
+    // Constellation peaks are thinned per second-long block of frames
+    let block = config(Normalization::None)
+        .time_base()
+        .frames_per_second()
+        .ceil() as usize;
+
+    // Loud until the clip starts, so a level history reaching back before it shows
+    let start = 3 * block;
+    let full: Vec<i16> = even(notes(rate, 0, 12.0))
+        .iter()
+        .enumerate()
+        .map(|(i, &s)| if i < start * hop { s } else { (s / 8) & !1 })
+        .collect();
+    let clip: Vec<i16> = full[start * hop..(start + 5 * block) * hop]
+        .iter()
+        .map(|&s| s / 2)
+        .collect();
+
+    // From the clip's second block on its history is the track's, and targets stay in the clip
+    let anchored = |set: &FingerprintSet, from: usize| -> Vec<(u32, usize)> {
+        set.fingerprints
+            .iter()
+            .filter(|fp| (from + block..from + 3 * block).contains(&fp.anchor_frame))
+            .map(|fp| (fp.hash, fp.anchor_frame - from))
+            .collect()
+    };
+
+    for normalization in Normalization::ALL {
+        let config = config(normalization);
+        let track = finger_print(&full, rate, &config).unwrap();
+        let excerpt = finger_print(&clip, rate, &config).unwrap();
+        let expected = anchored(&track, start);
+        assert!(!expected.is_empty());
+        assert_eq!(anchored(&excerpt, 0), expected, "{}", normalization.name());
+    }
+
+    // RMS levels of the clip's first second lack the loud history the track has
+    let spectra_agree = |normalization, frames: std::ops::Range<usize>| {
+        let config = config(normalization);
+        let track = analyze(&full, rate, &config).unwrap().spectrogram;
+        let excerpt = analyze(&clip, rate, &config).unwrap().spectrogram;
+        frames.into_iter().all(|t| {
+            excerpt[t]
+                .iter()
+                .zip(&track[start + t])
+                .all(|(a, b)| (a - b).abs() <= 1e-9 * b.abs().max(1.0))
+        })
+    };
+    assert!(!spectra_agree(Normalization::Rms, 0..block));
+    assert!(spectra_agree(Normalization::Rms, block..3 * block));
+    assert!(spectra_agree(Normalization::PerFrame, 0..3 * block));
+}
+
+#[test]
+fn a_click_only_affects_nearby_frames() {
+    let clean = even(notes(44100, 0, 6.0));
+    let mut clicked = clean.clone();
+    clicked[44100] = i16::MAX;
+
+    // Frames well past the click, and past the one second RMS history
+    let later = 3 * 11025 / 512;
+
+    for normalization in [
+        Normalization::Rms,
+        Normalization::PerFrame,
+        Normalization::LogMagnitude,
+    ] {
+        let config = config(normalization);
+        let a = analyze(&clean, 44100, &config).unwrap();
+        let b = analyze(&clicked, 44100, &config).unwrap();
+        assert_eq!(
+            a.spectrogram[later..],
+            b.spectrogram[later..],
+            "{}",
+            normalization.name()
+        );
+    }
+
+    // Peak normalization rescales the whole recording
+    let config = config(Normalization::Peak);
+    let a = analyze(&clean, 44100, &config).unwrap();
+    let b = analyze(&clicked, 44100, &config).unwrap();
+    assert_ne!(a.spectrogram[later..], b.spectrogram[later..]);
+}
+
+#[test]
+fn normalized_spectra_have_a_fixed_level() {
+    let samples = even(notes(44100, 0, 2.0));
+
+    let per_frame = analyze(&samples, 44100, &config(Normalization::PerFrame)).unwrap();
+    for spectrum in &per_frame.spectrogram {
+        let mean_square = spectrum.iter().map(|x| x * x).sum::<f64>() / spectrum.len() as f64;
+        assert!((mean_square - 1.0).abs() < 1e-9);
+    }
+
+    let log = analyze(&samples, 44100, &config(Normalization::LogMagnitude)).unwrap();
+    assert!(log
+        .spectrogram
+        .iter()
+        .flatten()
+        .all(|x| x.is_finite() && *x >= 0.0));
+}
+
+#[test]
+fn normalization_is_stored_with_the_config() {
+    let set = finger_print(
+        &even(notes(44100, 0, 2.0)),
+        44100,
+        &config(Normalization::LogMagnitude),
+    )
+    .unwrap();
+    let mut bytes = Vec::new();
+    write_fingerprints(&mut bytes, &set).unwrap();
+    assert_eq!(
+        read_fingerprints(&mut bytes.as_slice(), &set.config).unwrap(),
+        set
+    );
+    assert!(read_fingerprints(&mut bytes.as_slice(), &config(Normalization::Rms)).is_err());
+
+    // Stores of another format version are rejected
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.push(1);
+    assert!(read_fingerprints(&mut bytes.as_slice(), &set.config).is_err());
+}
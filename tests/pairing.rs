@@ -0,0 +1,171 @@
+use numero::fingerprint::peaks::Peak;
+use numero::{finger_print, hash_fingerprint, FingerprintConfig, Pairing, StreamingFingerprinter};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+/// Four peaks per frame over 40 frames, with magnitudes that vary by bin and frame
+fn peaks() -> Vec<Peak> {
+    (0..40)
+        .flat_map(|frame| {
+            [20, 90, 200, 400].into_iter().map(move |bin| Peak {
+                frame_index: frame,
+                freq_bin: bin + frame % 5,
+                magnitude: ((frame * 7 + bin) % 13) as f64,
+            })
+        })
+        .collect()
+}
+
+fn with_pairing(pairing: Pairing) -> FingerprintConfig {
+    FingerprintConfig {
+        pairing,
+        ..FingerprintConfig::music()
+    }
+}
+
+fn per_anchor(
+    fingerprints: &[numero::Fingerprint],
+    config: &FingerprintConfig,
+) -> HashMap<(usize, u32), usize> {
+    let mut counts = HashMap::new();
+    for fp in fingerprints {
+        let (f1, _, _) = config.hash_codec.decode(fp.hash);
+        *counts.entry((fp.anchor_frame, f1)).or_insert(0) += 1;
+    }
+    counts
+}
+
+#[test]
+fn fan_out_caps_hashes_per_anchor() {
+    let peaks = peaks();
+    let unlimited = with_pairing(Pairing::default());
+    let capped = with_pairing(Pairing {
+        max_fan_out: Some(5),
+        ..Pairing::default()
+    });
+
+    let all = hash_fingerprint(&peaks, &unlimited);
+    let limited = hash_fingerprint(&peaks, &capped);
+    assert!(limited.len() < all.len());
+    assert!(per_anchor(&limited, &capped).values().all(|&n| n <= 5));
+
+    // Without strongest-first the cap keeps the earliest targets, a prefix of each anchor's list
+    let mut prefix = Vec::new();
+    let mut current = None;
+    let mut taken = 0;
+    for fp in &all {
+        let anchor = (fp.anchor_frame, capped.hash_codec.decode(fp.hash).0);
+        if current != Some(anchor) {
+            current = Some(anchor);
+            taken = 0;
+        }
+        if taken < 5 {
+            prefix.push(*fp);
+            taken += 1;
+        }
+    }
+    assert_eq!(limited, prefix);
+}
+
+#[test]
+fn target_zone_shape_is_respected() {
+    let peaks = peaks();
+    let config = with_pairing(Pairing {
+        min_dt: 3,
+        max_freq_distance: Some(60),
+        ..Pairing::default()
+    });
+
+    let fingerprints = hash_fingerprint(&peaks, &config);
+    assert!(!fingerprints.is_empty());
+    for fp in &fingerprints {
+        let (f1, f2, dt) = config.hash_codec.decode(fp.hash);
+        assert!(dt >= 3 && dt as usize <= config.target_zone_frames);
+        assert!(f1.abs_diff(f2) <= 60);
+    }
+}
+
+#[test]
+fn strongest_first_keeps_loudest_targets() {
+    let peaks = peaks();
+    let config = with_pairing(Pairing {
+        max_fan_out: Some(3),
+        strongest_first: true,
+        ..Pairing::default()
+    });
+    let zone = config.target_zone_frames;
+
+    let fingerprints = hash_fingerprint(&peaks, &config);
+    let mut start = 0;
+    for (i, anchor) in peaks.iter().enumerate() {
+        let mut candidates: Vec<&Peak> = peaks[i + 1..]
+            .iter()
+            .take_while(|p| p.frame_index <= anchor.frame_index + zone)
+            .collect();
+        let count = candidates.len().min(3);
+        let emitted = &fingerprints[start..start + count];
+        start += count;
+
+        // The weakest chosen target is at least as strong as every rejected one
+        candidates.sort_by(|a, b| b.magnitude.total_cmp(&a.magnitude));
+        let chosen_min = emitted
+            .iter()
+            .map(|fp| {
+                let (_, f2, dt) = config.hash_codec.decode(fp.hash);
+                peaks[i + 1..]
+                    .iter()
+                    .find(|p| {
+                        p.frame_index == anchor.frame_index + dt as usize && p.freq_bin as u32 == f2
+                    })
+                    .unwrap()
+                    .magnitude
+            })
+            .fold(f64::INFINITY, f64::min);
+        if let Some(rejected) = candidates.get(count) {
+            assert!(chosen_min >= rejected.magnitude);
+        }
+    }
+    assert_eq!(start, fingerprints.len());
+}
+
+#[test]
+fn streaming_matches_batch_with_pairing_limits() {
+    let samples: Vec<i16> = (0..44100 * 4)
+        .map(|i| {
+            let t = i as f64 / 44100.0;
+            let f = 300.0 + 190.0 * ((t * 6.0) as usize % 13) as f64;
+            ((2.0 * PI * f * t).sin() * 12000.0 + (2.0 * PI * 2.3 * f * t).sin() * 6000.0) as i16
+        })
+        .collect();
+    let config = with_pairing(Pairing {
+        max_fan_out: Some(4),
+        min_dt: 1,
+        max_freq_distance: Some(200),
+        strongest_first: true,
+    });
+
+    let batch = finger_print(&samples, 44100, &config).unwrap();
+    let mut fingerprinter = StreamingFingerprinter::new(44100, &config).unwrap();
+    let mut streamed = Vec::new();
+    for part in samples.chunks(3001) {
+        streamed.extend(fingerprinter.push(part));
+    }
+    streamed.extend(fingerprinter.finish());
+    assert_eq!(streamed, batch.fingerprints);
+}
+
+#[test]
+fn invalid_pairing_is_rejected() {
+    let zero_fan_out = with_pairing(Pairing {
+        max_fan_out: Some(0),
+        ..Pairing::default()
+    });
+    assert!(zero_fan_out.validate().is_err());
+
+    let config = FingerprintConfig::music();
+    let beyond_zone = with_pairing(Pairing {
+        min_dt: config.target_zone_frames + 1,
+        ..Pairing::default()
+    });
+    assert!(beyond_zone.validate().is_err());
+}
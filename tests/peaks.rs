@@ -0,0 +1,99 @@
+mod common;
+
+use common::{noise, notes, with_noise};
+use numero::fingerprint::fingerprint::analyze;
+use numero::{find_match, finger_print, FingerprintConfig, PeakPicker, StreamingFingerprinter};
+
+const NEIGHBORHOOD_FRAMES: usize = 3;
+const NEIGHBORHOOD_BINS: usize = 8;
+const PEAKS_PER_SECOND: f64 = 30.0;
+
+fn constellation() -> FingerprintConfig {
+    FingerprintConfig {
+        peak_picker: PeakPicker::Constellation {
+            neighborhood_frames: NEIGHBORHOOD_FRAMES,
+            neighborhood_bins: NEIGHBORHOOD_BINS,
+            peaks_per_second: PEAKS_PER_SECOND,
+        },
+        ..FingerprintConfig::default()
+    }
+}
+
+#[test]
+fn constellation_peaks_are_local_maxima_at_the_target_density() {
+    let samples = with_noise(notes(44100, 0, 8.0), 500.0);
+    let config = constellation();
+    let analysis = analyze(&samples, 44100, &config).unwrap();
+    let spectrogram = &analysis.spectrogram;
+
+    let seconds = config.time_base().to_secs(spectrogram.len() as f64);
+    let density = analysis.peaks.len() as f64 / seconds;
+    assert!(
+        density > PEAKS_PER_SECOND * 0.5 && density < PEAKS_PER_SECOND * 1.2,
+        "{} peaks/s",
+        density
+    );
+
+    for peak in &analysis.peaks {
+        let t = peak.frame_index;
+        let f = peak.freq_bin;
+        for frame in &spectrogram[t.saturating_sub(NEIGHBORHOOD_FRAMES)
+            ..(t + NEIGHBORHOOD_FRAMES + 1).min(spectrogram.len())]
+        {
+            let bins = &frame
+                [f.saturating_sub(NEIGHBORHOOD_BINS)..(f + NEIGHBORHOOD_BINS + 1).min(frame.len())];
+            assert!(bins.iter().all(|&v| v <= peak.magnitude));
+        }
+    }
+
+    assert!(analysis
+        .peaks
+        .windows(2)
+        .all(|w| (w[0].frame_index, w[0].freq_bin) < (w[1].frame_index, w[1].freq_bin)));
+}
+
+#[test]
+fn noise_yields_far_fewer_peaks_than_band_maxima() {
+    let noise = noise(44100 * 4, 2048.0);
+
+    let band_max = analyze(&noise, 44100, &FingerprintConfig::default()).unwrap();
+    let constellation = analyze(&noise, 44100, &constellation()).unwrap();
+    assert!(constellation.peaks.len() * 3 < band_max.peaks.len());
+}
+
+#[test]
+fn streaming_matches_batch() {
+    let samples = with_noise(notes(48000, 0, 6.0), 500.0);
+    let config = constellation();
+    let batch = finger_print(&samples, 48000, &config).unwrap();
+
+    for chunk in [1000, 48000] {
+        let mut fingerprinter = StreamingFingerprinter::new(48000, &config).unwrap();
+        let mut streamed = Vec::new();
+        for part in samples.chunks(chunk) {
+            streamed.extend(fingerprinter.push(part));
+        }
+        streamed.extend(fingerprinter.finish());
+        assert_eq!(streamed, batch.fingerprints);
+    }
+}
+
+#[test]
+fn clip_is_found_in_the_recording() {
+    let config = constellation();
+    let song = with_noise(notes(44100, 0, 20.0), 500.0);
+    // Start on a frame boundary: 215 frames of 512 samples at 11025 Hz, i.e. 4 * 512 * 215 input samples
+    let start = 4 * 512 * 215;
+    let clip = &song[start..start + 44100 * 5];
+
+    let song_set = finger_print(&song, 44100, &config).unwrap();
+    let clip_set = finger_print(clip, 44100, &config).unwrap();
+    let found = find_match(
+        &song_set.fingerprints,
+        &clip_set.fingerprints,
+        &song_set.time_base(),
+    )
+    .unwrap();
+    assert_eq!(found.offset_frames, 215);
+    assert!((found.offset_secs - start as f64 / 44100.0).abs() < 1e-9);
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use common::{track, SAMPLE_RATE};
+use numero::{finger_print, FingerprintConfig, FingerprintIndex, MatchResult};
+
+#[test]
+fn query_ranks_every_candidate_with_metadata() {
+    let config = FingerprintConfig::music();
+    let mut index = FingerprintIndex::new(config.clone());
+    let songs: Vec<Vec<i16>> = (0..3).map(|seed| track(seed, 12.0)).collect();
+    for (i, song) in songs.iter().enumerate() {
+        let set = finger_print(song, SAMPLE_RATE, &config).unwrap();
+        index.add_track(&format!("song{}", i), &set).unwrap();
+    }
+
+    let start = 4 * SAMPLE_RATE as usize;
+    let clip_samples = &songs[1][start..start + 3 * SAMPLE_RATE as usize];
+    let clip = finger_print(clip_samples, SAMPLE_RATE, &config).unwrap();
+    let matches = index.query(&clip).unwrap();
+    let frame_secs = clip.time_base().seconds_per_frame();
+
+    // Every track shares some hashes with the clip and is listed, best first
+    assert_eq!(matches.len(), 3);
+    assert!(matches.windows(2).all(|w| w[0].votes >= w[1].votes));
+
+    let best = &matches[0];
+    assert_eq!(best.name, "song1");
+    assert!((best.offset_secs - 4.0).abs() < 2.0 * frame_secs);
+    assert!(best.covered_secs > 2.5 && best.covered_secs <= 3.0 + frame_secs);
+    assert!(best.score > 0.0 && best.score <= 1.0);
+    assert!(best.score > matches[1].score);
+    for m in &matches {
+        assert!(m.matched_hashes >= m.votes.min(1));
+        assert!(m.matched_hashes <= clip.len());
+    }
+}
+
+#[test]
+fn removed_tracks_leave_no_postings_behind() {
+    let config = FingerprintConfig::music();
+    let sets: Vec<_> = (0..4)
+        .map(|seed| finger_print(&track(seed, 8.0), SAMPLE_RATE, &config).unwrap())
+        .collect();
+    let mut index = FingerprintIndex::new(config.clone());
+    for (i, set) in sets.iter().enumerate() {
+        index.add_track(&format!("song{}", i), set).unwrap();
+    }
+
+    let removed = index.remove_track(1).unwrap();
+    assert_eq!((removed.id, removed.name.as_str()), (1, "song1"));
+    assert!(index.remove_track(1).is_none());
+    let removed: Vec<u32> = index
+        .remove_tracks(&[3, 7])
+        .iter()
+        .map(|info| info.id)
+        .collect();
+    assert_eq!(removed, vec![3]);
+
+    // Only the hashes of the remaining tracks are left
+    let mut kept = FingerprintIndex::new(config.clone());
+    kept.add_track("song0", &sets[0]).unwrap();
+    kept.add_track("song2", &sets[2]).unwrap();
+    assert_eq!(index.hash_count(), kept.hash_count());
+    assert_eq!(index.track_count(), 2);
+    assert!(index.track(1).is_none() && index.track(3).is_none());
+
+    assert!(index
+        .query(&sets[1])
+        .unwrap()
+        .iter()
+        .all(|m| m.track_id != 1));
+    // The other tracks still match as if the removed ones had never been added
+    let votes = |matches: Vec<MatchResult>| -> Vec<(String, usize)> {
+        matches.into_iter().map(|m| (m.name, m.votes)).collect()
+    };
+    for (id, set) in [(0, &sets[0]), (2, &sets[2])] {
+        let matches = index.query(set).unwrap();
+        assert_eq!(matches[0].track_id, id);
+        assert_eq!(votes(matches), votes(kept.query(set).unwrap()));
+    }
+}
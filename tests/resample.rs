@@ -0,0 +1,85 @@
+mod common;
+
+use common::notes;
+use numero::dsp::resample::Resampler;
+use numero::{find_match, finger_print, Fingerprint, FingerprintConfig};
+use std::collections::HashSet;
+use std::f64::consts::PI;
+
+fn sine(sample_rate: u32, freq: f64, len: usize) -> Vec<f64> {
+    (0..len)
+        .map(|i| (2.0 * PI * freq * i as f64 / sample_rate as f64).sin())
+        .collect()
+}
+
+#[test]
+fn output_length_matches_exact_ratio() {
+    let resampler = Resampler::new(48000, 11025, 101);
+    assert_eq!(resampler.ratio(), (147, 640));
+    assert_eq!(resampler.output_len(48000), 11025);
+    assert_eq!(resampler.process(&vec![0.0; 48000]).len(), 11025);
+
+    let resampler = Resampler::new(44100, 11025, 101);
+    assert_eq!(resampler.ratio(), (1, 4));
+    assert_eq!(resampler.output_len(44100), 11025);
+}
+
+#[test]
+fn passband_tone_keeps_frequency_and_amplitude() {
+    for input_rate in [44100, 48000, 96000] {
+        let input = sine(input_rate, 1000.0, input_rate as usize);
+        let output = Resampler::new(input_rate, 11025, 101).process(&input);
+        let expected = sine(11025, 1000.0, output.len());
+
+        // Ignore the filter's edge effects at both ends
+        let max_error = output[200..output.len() - 200]
+            .iter()
+            .zip(&expected[200..])
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f64::max);
+        assert!(
+            max_error < 0.02,
+            "{} Hz input: max error {}",
+            input_rate,
+            max_error
+        );
+    }
+}
+
+#[test]
+fn tone_above_target_nyquist_is_removed() {
+    let input = sine(48000, 8000.0, 48000);
+    let output = Resampler::new(48000, 11025, 101).process(&input);
+
+    let rms = (output[200..output.len() - 200]
+        .iter()
+        .map(|x| x * x)
+        .sum::<f64>()
+        / (output.len() - 400) as f64)
+        .sqrt();
+    assert!(rms < 0.01, "aliased energy left: rms {}", rms);
+}
+
+#[test]
+fn renditions_at_44100_and_48000_yield_matching_hashes() {
+    let config = FingerprintConfig::default();
+    let at_44k = finger_print(&notes(44100, 0, 10.0), 44100, &config).unwrap();
+    let at_48k = finger_print(&notes(48000, 0, 10.0), 48000, &config).unwrap();
+
+    let hashes_44k: HashSet<Fingerprint> = at_44k.fingerprints.iter().copied().collect();
+    let shared = at_48k
+        .fingerprints
+        .iter()
+        .filter(|fp| hashes_44k.contains(fp))
+        .count();
+    let ratio = shared as f64 / at_44k.len().min(at_48k.len()) as f64;
+    assert!(ratio > 0.8, "only {:.1}% of hashes shared", ratio * 100.0);
+
+    let matched = find_match(
+        &at_44k.fingerprints,
+        &at_48k.fingerprints,
+        &at_44k.time_base(),
+    )
+    .unwrap();
+    assert_eq!(matched.offset_frames, 0);
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use common::scratch_dir;
+use numero::index::store::{read_fingerprints, read_index, write_fingerprints, write_index};
+use numero::{
+    load_fingerprints, save_fingerprints, Fingerprint, FingerprintConfig, FingerprintIndex,
+    FingerprintSet,
+};
+
+fn set(config: &FingerprintConfig, hashes: &[(u32, usize)]) -> FingerprintSet {
+    FingerprintSet {
+        config: config.clone(),
+        fingerprints: hashes
+            .iter()
+            .map(|&(hash, anchor_frame)| Fingerprint { hash, anchor_frame })
+            .collect(),
+    }
+}
+
+fn index_bytes(index: &FingerprintIndex) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_index(&mut bytes, index).unwrap();
+    bytes
+}
+
+#[test]
+fn sets_and_indexes_round_trip() {
+    let config = FingerprintConfig::music();
+    let song = set(&config, &[(7, 0), (9, 3), (7, 12), (42, 20)]);
+
+    let path = std::env::temp_dir().join(format!("numero-store-{}.numr", std::process::id()));
+    save_fingerprints(&path, &song).unwrap();
+    assert_eq!(load_fingerprints(&path, &config).unwrap(), song);
+    assert!(load_fingerprints(&path, &FingerprintConfig::speech()).is_err());
+
+    let mut index = FingerprintIndex::new(config.clone());
+    index.add_track("first", &song).unwrap();
+    index.add_track("second", &set(&config, &[(9, 5)])).unwrap();
+    index.save(&path).unwrap();
+    let loaded = FingerprintIndex::load(&path, &config).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.track_count(), 2);
+    assert_eq!(loaded.hash_count(), index.hash_count());
+    let clip = set(&config, &[(7, 2), (9, 5), (7, 14), (42, 22)]);
+    assert_eq!(loaded.query(&clip).unwrap(), index.query(&clip).unwrap());
+}
+
+#[test]
+fn truncated_stores_are_rejected() {
+    let config = FingerprintConfig::music();
+    let song = set(&config, &[(7, 0), (9, 3)]);
+
+    let mut bytes = Vec::new();
+    write_fingerprints(&mut bytes, &song).unwrap();
+    for len in 0..bytes.len() {
+        assert!(read_fingerprints(&mut &bytes[..len], &config).is_err());
+    }
+
+    let mut index = FingerprintIndex::new(config.clone());
+    index.add_track("song", &song).unwrap();
+    let bytes = index_bytes(&index);
+    for len in 0..bytes.len() {
+        assert!(read_index(&mut &bytes[..len], &config).is_err());
+    }
+}
+
+#[test]
+fn corrupt_lengths_fail_without_allocating_them() {
+    let config = FingerprintConfig::music();
+    let empty = index_bytes(&FingerprintIndex::new(config.clone()));
+    // Next id, track count, hash count and manifest count follow the header
+    let header = empty.len() - 4 - 4 - 8 - 4;
+
+    let mut index = FingerprintIndex::new(config.clone());
+    index.add_track("a", &set(&config, &[(7, 0)])).unwrap();
+    let bytes = index_bytes(&index);
+
+    // Track name length: after next id, track count and track id
+    let mut corrupt = bytes.clone();
+    let name_len = header + 12;
+    corrupt[name_len..name_len + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(read_index(&mut corrupt.as_slice(), &config).is_err());
+
+    // Posting count: after the name, fingerprint count, hash count and hash
+    let mut corrupt = bytes.clone();
+    let posting_count = name_len + 4 + 1 + 8 + 8 + 4;
+    assert_eq!(
+        &corrupt[posting_count..posting_count + 4],
+        &1u32.to_le_bytes()
+    );
+    corrupt[posting_count..posting_count + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(read_index(&mut corrupt.as_slice(), &config).is_err());
+
+    // Fingerprint count of a set
+    let mut corrupt = Vec::new();
+    write_fingerprints(&mut corrupt, &set(&config, &[(7, 0)])).unwrap();
+    let count = corrupt.len() - 8 - 8;
+    corrupt[count..count + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(read_fingerprints(&mut corrupt.as_slice(), &config).is_err());
+}
+
+#[test]
+fn saving_replaces_the_file_whole_or_not_at_all() {
+    let config = FingerprintConfig::music();
+    let dir = scratch_dir("store", "replace");
+    let path = dir.join("index.numr");
+
+    let mut index = FingerprintIndex::new(config.clone());
+    index.add_track("first", &set(&config, &[(7, 0)])).unwrap();
+    index.save(&path).unwrap();
+    index.add_track("second", &set(&config, &[(9, 5)])).unwrap();
+    index.save(&path).unwrap();
+    assert_eq!(
+        FingerprintIndex::load(&path, &config)
+            .unwrap()
+            .track_count(),
+        2
+    );
+
+    // A save that cannot complete leaves neither its temporary file nor a changed target
+    let occupied = dir.join("occupied");
+    std::fs::create_dir_all(occupied.join("inside")).unwrap();
+    assert!(index.save(&occupied).is_err());
+    assert!(occupied.join("inside").is_dir());
+    assert!(save_fingerprints(dir.join("missing/set.numr"), &set(&config, &[(7, 0)])).is_err());
+
+    let mut names: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["index.numr", "occupied"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
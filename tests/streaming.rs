@@ -0,0 +1,91 @@
+mod common;
+
+use common::{notes, with_spike};
+use numero::dsp::resample::Resampler;
+use numero::{finger_print, FingerprintConfig, StreamingFingerprinter};
+
+fn stream(
+    samples: &[i16],
+    sample_rate: u32,
+    chunk: usize,
+    config: &FingerprintConfig,
+) -> Vec<numero::Fingerprint> {
+    let mut fingerprinter = StreamingFingerprinter::new(sample_rate, config).unwrap();
+    let mut fingerprints = Vec::new();
+    for part in samples.chunks(chunk) {
+        fingerprints.extend(fingerprinter.push(part));
+    }
+    fingerprints.extend(fingerprinter.finish());
+    fingerprints
+}
+
+#[test]
+fn resampler_stream_matches_batch() {
+    let input: Vec<f64> = (0..20_000).map(|i| (i as f64 * 0.01).sin()).collect();
+    for (from, to) in [
+        (48000, 11025),
+        (44100, 11025),
+        (11025, 11025),
+        (96000, 8000),
+    ] {
+        let resampler = Resampler::new(from, to, 101);
+        let expected = resampler.process(&input);
+
+        for chunk in [1, 7, 1000, input.len()] {
+            let mut stream = resampler.clone().into_stream();
+            let mut output = Vec::new();
+            for part in input.chunks(chunk) {
+                stream.push(part, &mut output);
+            }
+            stream.finish(&mut output);
+            assert_eq!(
+                output, expected,
+                "{} -> {} Hz in chunks of {}",
+                from, to, chunk
+            );
+        }
+    }
+}
+
+#[test]
+fn streaming_matches_batch_for_any_chunking() {
+    for (sample_rate, config) in [
+        (44100, FingerprintConfig::music()),
+        (48000, FingerprintConfig::music()),
+        (48000, FingerprintConfig::speech()),
+    ] {
+        let samples = with_spike(notes(sample_rate, 0, 6.0));
+        let batch = finger_print(&samples, sample_rate, &config).unwrap();
+
+        for chunk in [333, 4096, samples.len()] {
+            let streamed = stream(&samples, sample_rate, chunk, &config);
+            assert_eq!(
+                streamed, batch.fingerprints,
+                "{} Hz in chunks of {}",
+                sample_rate, chunk
+            );
+        }
+    }
+}
+
+#[test]
+fn fingerprints_are_emitted_before_the_stream_ends() {
+    let config = FingerprintConfig::default();
+    let samples = with_spike(notes(44100, 0, 6.0));
+    let mut fingerprinter = StreamingFingerprinter::new(44100, &config).unwrap();
+
+    let mut early = 0;
+    for part in samples.chunks(4410) {
+        early += fingerprinter.push(part).len();
+    }
+    let late = fingerprinter.finish().len();
+
+    // Only anchors within the last target zone wait for the end of the stream
+    assert!(early > 0);
+    assert!(late < early / 2, "{} early, {} late", early, late);
+}
+
+#[test]
+fn rejects_rates_below_the_target() {
+    assert!(StreamingFingerprinter::new(8000, &FingerprintConfig::music()).is_err());
+}
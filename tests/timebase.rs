@@ -0,0 +1,40 @@
+mod common;
+
+use common::notes;
+use numero::{finger_print, FingerprintConfig, FingerprintIndex, TimeBase};
+
+#[test]
+fn frame_duration_follows_the_config() {
+    let music = FingerprintConfig::music().time_base();
+    assert_eq!(music.seconds_per_frame(), 512.0 / 11025.0);
+    assert_eq!(
+        FingerprintConfig::speech().time_base(),
+        TimeBase::new(8000, 256)
+    );
+    assert_eq!(
+        FingerprintConfig::broadcast()
+            .time_base()
+            .seconds_per_frame(),
+        256.0 / 11025.0
+    );
+
+    let secs = music.to_secs(215.0);
+    assert!((music.to_frames(secs) - 215.0).abs() < 1e-9);
+}
+
+#[test]
+fn query_reports_offsets_in_the_index_time_base() {
+    // Half the hop of the music preset, so frames are half as long
+    let config = FingerprintConfig::broadcast();
+    let song = notes(44100, 0, 10.0);
+    let mut index = FingerprintIndex::new(config.clone());
+    let set = finger_print(&song, 44100, &config).unwrap();
+    index.add_track("song", &set).unwrap();
+
+    // 4 * 256 * 172 input samples: frame 172, just under 4 seconds
+    let start = 4 * 256 * 172;
+    let clip = finger_print(&song[start..start + 44100 * 3], 44100, &config).unwrap();
+    let best = &index.query(&clip).unwrap()[0];
+    assert_eq!(best.offset_frames, 172);
+    assert!((best.offset_secs - start as f64 / 44100.0).abs() < 1e-9);
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use common::{chimes, SAMPLE_RATE};
+use numero::fingerprint::triplet::triplet_hash;
+use numero::{
+    find_match, find_scaled_match, finger_print, finger_print_triplets, FingerprintConfig,
+    FingerprintIndex, HashFamily, NumeroError, Peak, StreamingFingerprinter,
+};
+
+/// `seconds` of `chimes` at `SAMPLE_RATE`, kept unquantized for `excerpt` to resample
+fn song(seconds: f64) -> Vec<f64> {
+    (0..(SAMPLE_RATE as f64 * seconds) as usize)
+        .map(|i| chimes(0, i as f64 / SAMPLE_RATE as f64))
+        .collect()
+}
+
+/// `seconds` of `signal` from `start`, played `speed` times faster like a sped-up turntable
+fn excerpt(signal: &[f64], start: f64, seconds: f64, speed: f64) -> Vec<i16> {
+    let offset = start * SAMPLE_RATE as f64;
+    (0..(seconds * SAMPLE_RATE as f64) as usize)
+        .map(|i| {
+            let pos = offset + i as f64 * speed;
+            let k = pos as usize;
+            let frac = pos - k as f64;
+            let x = signal[k] * (1.0 - frac) + signal[k + 1] * frac;
+            (x * 20000.0) as i16
+        })
+        .collect()
+}
+
+fn peak(frame_index: usize, freq_bin: usize) -> Peak {
+    Peak {
+        frame_index,
+        freq_bin,
+        magnitude: 1.0,
+    }
+}
+
+#[test]
+fn triplet_hash_ignores_time_and_frequency_scale() {
+    let anchor = peak(10, 40);
+    let original = triplet_hash(&anchor, (&peak(13, 60), 3), (&peak(19, 90), 9));
+    // Twice as slow and an octave up
+    let scaled = triplet_hash(&peak(20, 80), (&peak(26, 120), 6), (&peak(38, 180), 18));
+    assert_eq!(original, scaled);
+
+    let other = triplet_hash(&anchor, (&peak(13, 70), 3), (&peak(19, 90), 9));
+    assert_ne!(original, other);
+}
+
+#[test]
+fn scaled_match_recovers_speed_change() {
+    let config = FingerprintConfig::music();
+    let song = song(16.0);
+    let reference = excerpt(&song, 0.0, 15.0, 1.0);
+    let clip = excerpt(&song, 8.0, 6.0, 1.05);
+
+    let song_triplets = finger_print_triplets(&reference, SAMPLE_RATE, &config).unwrap();
+    let clip_triplets = finger_print_triplets(&clip, SAMPLE_RATE, &config).unwrap();
+    let found = find_scaled_match(&song_triplets, &clip_triplets, &config.time_base()).unwrap();
+
+    assert!((found.tempo_scale - 1.05).abs() < 0.01, "{:?}", found);
+    assert!((found.pitch_scale - 1.05).abs() < 0.02, "{:?}", found);
+    let expected = config.time_base().to_frames(8.0);
+    assert!(
+        (found.offset_frames as f64 - expected).abs() <= 3.0,
+        "{:?}",
+        found
+    );
+
+    // Pair hashes of the same clip find far less agreement
+    let pairs = |samples: &[i16]| {
+        finger_print(samples, SAMPLE_RATE, &config)
+            .unwrap()
+            .fingerprints
+    };
+    let pair_votes =
+        find_match(&pairs(&reference), &pairs(&clip), &config.time_base()).map_or(0, |m| m.votes);
+    assert!(
+        found.votes > pair_votes,
+        "{} vs {}",
+        found.votes,
+        pair_votes
+    );
+}
+
+#[test]
+fn triplet_family_fingerprints_and_streams() {
+    let config = FingerprintConfig {
+        hash_family: HashFamily::Triplet,
+        ..FingerprintConfig::music()
+    };
+    let samples = excerpt(&song(5.0), 0.0, 4.5, 1.0);
+
+    let set = finger_print(&samples, SAMPLE_RATE, &config).unwrap();
+    let triplets = finger_print_triplets(&samples, SAMPLE_RATE, &config).unwrap();
+    assert!(!set.is_empty());
+    assert!(set
+        .fingerprints
+        .iter()
+        .zip(&triplets)
+        .all(|(fp, triplet)| fp.hash == triplet.hash && fp.anchor_frame == triplet.anchor_frame));
+
+    let mut fingerprinter = StreamingFingerprinter::new(SAMPLE_RATE, &config).unwrap();
+    let mut streamed = Vec::new();
+    for part in samples.chunks(4000) {
+        streamed.extend(fingerprinter.push(part));
+    }
+    streamed.extend(fingerprinter.finish());
+    assert_eq!(streamed, set.fingerprints);
+}
+
+#[test]
+fn index_rejects_triplet_hashes() {
+    let config = FingerprintConfig {
+        hash_family: HashFamily::Triplet,
+        ..FingerprintConfig::music()
+    };
+    let set = finger_print(&excerpt(&song(5.0), 0.0, 4.5, 1.0), SAMPLE_RATE, &config).unwrap();
+
+    let mut index = FingerprintIndex::new(config);
+    assert!(matches!(
+        index.add_track("song", &set),
+        Err(NumeroError::InvalidConfig(_))
+    ));
+    assert!(matches!(
+        index.query(&set),
+        Err(NumeroError::InvalidConfig(_))
+    ));
+    assert_eq!(index.track_count(), 0);
+}
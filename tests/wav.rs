@@ -0,0 +1,121 @@
+mod common;
+
+use common::{interleave, notes, scratch_dir, with_spike, write_wav};
+use numero::{
+    finger_print, finger_print_blocks, open_audio_blocks, read_audio_file, BatchIndexer,
+    FingerprintConfig, FingerprintIndex,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Writes interleaved 16-bit PCM as a WAV file in a fresh directory
+fn wav_file(name: &str, samples: &[i16], sample_rate: u32, channels: u16) -> PathBuf {
+    let path = scratch_dir("wav", name).join("audio.wav");
+    write_wav(&path, samples, sample_rate, channels);
+    path
+}
+
+/// Different notes on each channel, with one full-scale frame
+fn stereo_signal(sample_rate: u32, seconds: f64) -> Vec<i16> {
+    interleave(
+        &with_spike(notes(sample_rate, 0, seconds)),
+        &with_spike(notes(sample_rate, 1, seconds)),
+    )
+}
+
+fn remove(path: &Path) {
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn blocks_concatenate_to_the_whole_file() {
+    let stereo = stereo_signal(44100, 2.0);
+    let path = wav_file("blocks", &stereo, 44100, 2);
+
+    let (samples, sample_rate) = read_audio_file(&path).unwrap();
+    assert_eq!(sample_rate, 44100);
+    assert_eq!(samples.len(), stereo.len() / 2);
+    assert_eq!(
+        samples[1],
+        ((stereo[2] as i32 + stereo[3] as i32) / 2) as i16
+    );
+
+    assert!(open_audio_blocks(&path, 0).is_err());
+    let blocks = open_audio_blocks(&path, 1000).unwrap();
+    assert_eq!(blocks.channels(), 2);
+    let blocks: Vec<Vec<i16>> = blocks.collect();
+    assert!(blocks[..blocks.len() - 1].iter().all(|b| b.len() == 1000));
+    assert_eq!(blocks.concat(), samples);
+
+    remove(&path);
+}
+
+#[test]
+fn block_fingerprints_match_whole_file_fingerprints() {
+    let path = wav_file("fingerprint", &stereo_signal(48000, 5.0), 48000, 2);
+    let config = FingerprintConfig::default();
+
+    let (samples, sample_rate) = read_audio_file(&path).unwrap();
+    let whole = finger_print(&samples, sample_rate, &config).unwrap();
+
+    let blocks = open_audio_blocks(&path, 4096).unwrap();
+    let sample_rate = blocks.sample_rate();
+    let streamed = finger_print_blocks(blocks, sample_rate, &config).unwrap();
+    assert_eq!(streamed, whole);
+
+    remove(&path);
+}
+
+#[test]
+fn any_rate_down_to_the_target_rate_is_accepted() {
+    let path = wav_file("rate", &stereo_signal(22050, 2.0), 22050, 2);
+
+    let blocks = open_audio_blocks(&path, 4096).unwrap();
+    assert_eq!(blocks.sample_rate(), 22050);
+    let streamed = finger_print_blocks(blocks, 22050, &FingerprintConfig::music()).unwrap();
+    assert!(!streamed.is_empty());
+    remove(&path);
+
+    // 8 kHz decodes, but only reaches the target rate of the speech preset
+    let path = wav_file("low-rate", &stereo_signal(8000, 2.0), 8000, 2);
+    let (samples, sample_rate) = read_audio_file(&path).unwrap();
+    assert_eq!(sample_rate, 8000);
+    assert!(finger_print(&samples, sample_rate, &FingerprintConfig::music()).is_err());
+    assert!(finger_print(&samples, sample_rate, &FingerprintConfig::speech()).is_ok());
+    remove(&path);
+}
+
+#[test]
+fn streamed_and_whole_reads_reject_the_same_files() {
+    // Full-scale jumps between every sample, as a bad downmix would produce
+    let jumpy: Vec<i16> = (0..22050)
+        .map(|i| if i % 2 == 0 { 0 } else { 30000 })
+        .collect();
+    for (name, samples) in [("jumpy", jumpy), ("empty", Vec::new())] {
+        let path = wav_file(name, &samples, 22050, 1);
+        assert!(read_audio_file(&path).is_err(), "{}", name);
+
+        let mut blocks = open_audio_blocks(&path, 4096).unwrap();
+        blocks.by_ref().for_each(drop);
+        assert!(blocks.check_format().is_err(), "{}", name);
+
+        let mut index = FingerprintIndex::new(FingerprintConfig::music());
+        let report = BatchIndexer::new()
+            .index_files(std::slice::from_ref(&path), &mut index, |_| {})
+            .unwrap();
+        assert_eq!(report.skipped.len(), 1, "{}", name);
+        assert_eq!(index.track_count(), 0);
+
+        remove(&path);
+    }
+
+    let path = wav_file("smooth", &stereo_signal(22050, 1.0), 22050, 2);
+    let mut blocks = open_audio_blocks(&path, 4096).unwrap();
+    assert_eq!(
+        blocks.by_ref().map(|block| block.len()).sum::<usize>(),
+        22050
+    );
+    assert_eq!(blocks.samples_read(), 22050);
+    blocks.check_format().unwrap();
+    remove(&path);
+}